@@ -0,0 +1,46 @@
+//! Prepares the embedded rules, dropping the parts disabled by cargo features.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=data.minify.json");
+    // set by `pyproject.toml` for the Python module, see the `polars` module
+    println!("cargo:rustc-check-cfg=cfg(clearurls_polars_plugin)");
+
+    #[cfg(feature = "node")]
+    napi_build::setup();
+
+    let strip_referral_marketing =
+        env::var_os("CARGO_FEATURE_STRIP_EMBEDDED_REFERRAL_MARKETING").is_some();
+    let strip_redirections = env::var_os("CARGO_FEATURE_STRIP_EMBEDDED_REDIRECTIONS").is_some();
+
+    let data = fs::read_to_string("data.minify.json").expect("failed to read data.minify.json");
+    let data = if strip_referral_marketing || strip_redirections {
+        let mut rules: serde_json::Value =
+            serde_json::from_str(&data).expect("data.minify.json is not valid json");
+        let providers = rules["providers"]
+            .as_object_mut()
+            .expect("data.minify.json has no providers map");
+        for provider in providers.values_mut() {
+            let provider = provider
+                .as_object_mut()
+                .expect("data.minify.json has a provider that is not an object");
+            if strip_referral_marketing {
+                provider.remove("referralMarketing");
+            }
+            if strip_redirections {
+                provider.remove("redirections");
+                provider.remove("forceRedirection");
+            }
+        }
+        rules.to_string()
+    } else {
+        data
+    };
+
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").expect("OUT_DIR is not set"));
+    fs::write(out_dir.join("data.minify.json"), data).expect("failed to write embedded rules");
+}
@@ -0,0 +1,17 @@
+//! Load arbitrary rules, and clean arbitrary URLs with the ones that compile.
+
+#![no_main]
+
+use clearurls::arbitrary::{ArbitraryRules, ArbitraryUrl};
+use clearurls::UrlCleaner;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: (ArbitraryRules, Vec<ArbitraryUrl>)| {
+    let (rules, urls) = input;
+    let Ok(cleaner) = UrlCleaner::from_rules_str(&rules.to_json()) else {
+        return;
+    };
+    for url in urls {
+        let _ = cleaner.clear_url(&url.0);
+    }
+});
@@ -0,0 +1,16 @@
+//! Clean arbitrary URLs with the embedded rules.
+
+#![no_main]
+
+use std::sync::OnceLock;
+
+use clearurls::arbitrary::ArbitraryUrl;
+use clearurls::UrlCleaner;
+use libfuzzer_sys::fuzz_target;
+
+static CLEANER: OnceLock<UrlCleaner> = OnceLock::new();
+
+fuzz_target!(|url: ArbitraryUrl| {
+    let cleaner = CLEANER.get_or_init(|| UrlCleaner::from_embedded_rules().unwrap());
+    let _ = cleaner.clear_url(&url.0);
+});
@@ -0,0 +1,106 @@
+#ifndef CLEARURLS_H
+#define CLEARURLS_H
+
+/* Generated with cbindgen from src/ffi.rs, do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * The result of a function of the C API.
+ */
+typedef enum ClearUrlsStatus {
+  /**
+   * Success
+   */
+  CLEAR_URLS_STATUS_OK = 0,
+  /**
+   * A pointer argument was null
+   */
+  CLEAR_URLS_STATUS_NULL_POINTER = 1,
+  /**
+   * A string argument is not valid UTF-8
+   */
+  CLEAR_URLS_STATUS_INVALID_UTF8 = 2,
+  /**
+   * The rules are invalid JSON, or not in the format of the browser extension
+   */
+  CLEAR_URLS_STATUS_RULE_SYNTAX = 3,
+  /**
+   * The URL can't be parsed, or has syntax violations that the parse mode rejects
+   */
+  CLEAR_URLS_STATUS_URL_SYNTAX = 4,
+  /**
+   * A redirection can't be followed, like one that leads to itself
+   */
+  CLEAR_URLS_STATUS_REDIRECTION = 5,
+  /**
+   * The cleaned URL contains a NUL byte, so it can't be returned as a C string
+   */
+  CLEAR_URLS_STATUS_INTERIOR_NUL = 6,
+  /**
+   * Any other error
+   */
+  CLEAR_URLS_STATUS_OTHER = 255,
+} ClearUrlsStatus;
+
+/**
+ * A `UrlCleaner` can remove tracking parameters from URLs.
+ *
+ * This struct is relatively expensive to construct because it needs to parse the rules from JSON.
+ * It's recommended to create one per application and reuse it.
+ */
+typedef struct ClearUrlsCleaner ClearUrlsCleaner;
+
+/**
+ * Construct a cleaner with the rules embedded in the library.
+ *
+ * # Returns
+ * the cleaner, which must be freed with `clearurls_free`, or null if the embedded rules
+ * can't be parsed
+ */
+struct ClearUrlsCleaner *clearurls_new(void);
+
+/**
+ * Construct a cleaner with rules in the JSON format of the browser extension. On success,
+ * the cleaner is written to `out`, and must be freed with `clearurls_free`.
+ *
+ * # Safety
+ * `rules` must be null or a NUL-terminated string, and `out` must be null or valid for writes.
+ */
+enum ClearUrlsStatus clearurls_from_rules(const char *rules, struct ClearUrlsCleaner **out);
+
+/**
+ * Clean a URL. On success, the cleaned URL is written to `out`, and must be freed with
+ * `clearurls_string_free`.
+ *
+ * # Safety
+ * `cleaner` must be null or returned by `clearurls_new` or `clearurls_from_rules` and
+ * not freed yet, `url` must be null or a NUL-terminated string, and `out` must be null or
+ * valid for writes.
+ */
+enum ClearUrlsStatus clearurls_clean(const struct ClearUrlsCleaner *cleaner,
+                                     const char *url,
+                                     char **out);
+
+/**
+ * Free a URL returned by `clearurls_clean`. Null is ignored.
+ *
+ * # Safety
+ * `s` must be null or returned by `clearurls_clean` and not freed yet.
+ */
+void clearurls_string_free(char *s);
+
+/**
+ * Free a cleaner. Null is ignored.
+ *
+ * # Safety
+ * `cleaner` must be null or returned by `clearurls_new` or `clearurls_from_rules` and
+ * not freed yet.
+ */
+void clearurls_free(struct ClearUrlsCleaner *cleaner);
+
+#endif  /* CLEARURLS_H */
@@ -0,0 +1,289 @@
+//! Anonymizing web server access logs before they are retained, by cleaning or redacting the
+//! tracking parameters in their request URLs and referrers.
+//!
+//! [`LogCleaner`] reads the Common Log Format and the Combined Log Format of Apache and nginx,
+//! and JSON lines. Lines in other formats are kept as they are.
+//!
+//! ```
+//! # use clearurls::UrlCleaner;
+//! # use clearurls::access_log::{LogCleaner, Mode};
+//! # fn main() -> Result<(), clearurls::Error> {
+//! let cleaner = UrlCleaner::from_embedded_rules()?;
+//! let line = r#"127.0.0.1 - - [10/Oct/2024:13:55:36 +0000] "GET /a?id=1&utm_source=x HTTP/1.1" 200 2326"#;
+//! let logs = LogCleaner::new(&cleaner).host("example.com");
+//! assert_eq!(
+//!     logs.clean_line(line),
+//!     r#"127.0.0.1 - - [10/Oct/2024:13:55:36 +0000] "GET /a?id=1 HTTP/1.1" 200 2326"#,
+//! );
+//! assert_eq!(
+//!     logs.mode(Mode::Redact).clean_line(line),
+//!     r#"127.0.0.1 - - [10/Oct/2024:13:55:36 +0000] "GET /a?id=1&utm_source=REDACTED HTTP/1.1" 200 2326"#,
+//! );
+//! # Ok(())
+//! # }
+//! ```
+
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::ops::Range;
+use std::io::{self, BufRead, Write};
+
+use serde_json::Value;
+
+use crate::UrlCleaner;
+
+/// The fields of JSON lines that a [`LogCleaner`] cleans by default.
+pub const DEFAULT_JSON_FIELDS: &[&str] = &[
+    "http_referer",
+    "referer",
+    "referrer",
+    "request",
+    "request_uri",
+    "uri",
+    "url",
+];
+
+/// What a [`LogCleaner`] does with tracking parameters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Mode {
+    /// Replace URLs with the cleaned ones, like [`UrlCleaner::clear_url`] does.
+    #[default]
+    Clean,
+    /// Keep the names of the parameters that would be removed, and replace their values with
+    /// `REDACTED`, so that it stays visible that there were some. Parameters without a value
+    /// get `=REDACTED` too. URLs with redirections are replaced with the cleaned ones.
+    Redact,
+}
+
+/// Cleans the request URLs and referrers of access logs, see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct LogCleaner<'a> {
+    cleaner: &'a UrlCleaner,
+    host: String,
+    mode: Mode,
+    json_fields: Vec<String>,
+}
+
+impl<'a> LogCleaner<'a> {
+    /// Construct a [`LogCleaner`] with the host `localhost`, in [`Mode::Clean`].
+    #[must_use]
+    pub fn new(cleaner: &'a UrlCleaner) -> Self {
+        Self {
+            cleaner,
+            host: "localhost".to_string(),
+            mode: Mode::default(),
+            json_fields: DEFAULT_JSON_FIELDS
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+        }
+    }
+
+    /// The host that requests without one, like `GET /path`, were made to, so that the rules
+    /// of its provider apply.
+    #[must_use]
+    pub fn host(mut self, host: &str) -> Self {
+        self.host = host.to_string();
+        self
+    }
+
+    /// What to do with tracking parameters. The default is [`Mode::Clean`].
+    #[must_use]
+    pub fn mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// The fields of JSON lines to clean. The default is [`DEFAULT_JSON_FIELDS`].
+    ///
+    /// Fields with a request line, like `GET /path HTTP/1.1`, are cleaned too.
+    #[must_use]
+    pub fn json_fields<I, S>(mut self, fields: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.json_fields = fields.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Clean a line of a log, without its line ending.
+    #[must_use]
+    pub fn clean_line<'l>(&self, line: &'l str) -> Cow<'l, str> {
+        if line.trim_start().starts_with('{') {
+            self.clean_json(line)
+        } else {
+            self.clean_combined(line)
+        }
+    }
+
+    /// Read a log from `reader`, clean each line, write them to `writer`, and return how many
+    /// lines were changed.
+    ///
+    /// # Errors
+    /// If the log can't be read or written, or isn't valid UTF-8.
+    pub fn clean<R: BufRead, W: Write>(&self, reader: R, mut writer: W) -> io::Result<usize> {
+        let mut changed = 0;
+        for line in reader.lines() {
+            let line = line?;
+            let result = self.clean_line(&line);
+            changed += usize::from(matches!(result, Cow::Owned(_)));
+            writer.write_all(result.as_bytes())?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()?;
+        Ok(changed)
+    }
+
+    /// Clean the request and the referrer of a line in the Common or Combined Log Format.
+    fn clean_combined<'l>(&self, line: &'l str) -> Cow<'l, str> {
+        let mut edits: Vec<(Range<usize>, String)> = Vec::new();
+        let mut fields = quoted_fields(line);
+        if let Some(request) = fields.next() {
+            if let Some((range, result)) = self.clean_request(&line[request.clone()]) {
+                edits.push((
+                    request.start + range.start..request.start + range.end,
+                    result,
+                ));
+            }
+        }
+        if let Some(referrer) = fields.next() {
+            if let Some(result) = self.clean_url(&line[referrer.clone()]) {
+                edits.push((referrer, result));
+            }
+        }
+        if edits.is_empty() {
+            return Cow::Borrowed(line);
+        }
+        let mut result = String::with_capacity(line.len());
+        let mut copied = 0;
+        for (range, replacement) in edits {
+            result.push_str(&line[copied..range.start]);
+            result.push_str(&replacement);
+            copied = range.end;
+        }
+        result.push_str(&line[copied..]);
+        Cow::Owned(result)
+    }
+
+    fn clean_json<'l>(&self, line: &'l str) -> Cow<'l, str> {
+        let Ok(Value::Object(mut object)) = serde_json::from_str(line) else {
+            return Cow::Borrowed(line);
+        };
+        let mut changed = false;
+        for field in &self.json_fields {
+            let Some(Value::String(value)) = object.get_mut(field) else {
+                continue;
+            };
+            let result = match self.clean_request(value) {
+                Some((range, result)) => {
+                    Some([&value[..range.start], &result, &value[range.end..]].concat())
+                }
+                None => self.clean_url(value),
+            };
+            if let Some(result) = result {
+                *value = result;
+                changed = true;
+            }
+        }
+        match serde_json::to_string(&object) {
+            Ok(result) if changed => Cow::Owned(result),
+            _ => Cow::Borrowed(line),
+        }
+    }
+
+    /// The range of the target in a request line like `GET /path HTTP/1.1`, and its cleaned
+    /// replacement, if it changed.
+    fn clean_request(&self, request: &str) -> Option<(Range<usize>, String)> {
+        let mut parts = request.splitn(3, ' ');
+        let method = parts.next()?;
+        let target = parts.next()?;
+        if method.is_empty() || !method.bytes().all(|b| b.is_ascii_uppercase()) {
+            return None;
+        }
+        let start = method.len() + 1;
+        Some((start..start + target.len(), self.clean_url(target)?))
+    }
+
+    /// The cleaned or redacted URL or path, if it changed.
+    fn clean_url(&self, url: &str) -> Option<String> {
+        let base = ["http://", &self.host].concat();
+        let absolute = if url.starts_with('/') {
+            Cow::Owned([&base, url].concat())
+        } else {
+            Cow::Borrowed(url)
+        };
+        let report = self.cleaner.clear_url_with_report(&absolute).ok()?;
+        if report.url == absolute {
+            return None;
+        }
+        let result = if self.mode == Mode::Redact && report.redirections.is_empty() {
+            redact(url, &report.removed_parameters)
+        } else if url.starts_with('/') {
+            let path = report
+                .url
+                .strip_prefix(&*base)
+                .filter(|path| path.starts_with('/'));
+            path.map_or_else(|| report.url.to_string(), ToString::to_string)
+        } else {
+            report.url.into_owned()
+        };
+        Some(result)
+    }
+}
+
+/// Replace the values of the query and fragment parameters of `url` that are one of
+/// `parameters`, like [`Report::removed_parameters`](crate::Report::removed_parameters).
+fn redact(url: &str, parameters: &[String]) -> String {
+    let mut result = String::with_capacity(url.len());
+    let (url, fragment) = url
+        .split_once('#')
+        .map_or((url, None), |(url, fragment)| (url, Some(fragment)));
+    let (path, query) = url
+        .split_once('?')
+        .map_or((url, None), |(path, query)| (path, Some(query)));
+    result.push_str(path);
+    for (separator, part) in [('?', query), ('#', fragment)] {
+        let Some(part) = part else {
+            continue;
+        };
+        result.push(separator);
+        for (i, pair) in part.split('&').enumerate() {
+            if i > 0 {
+                result.push('&');
+            }
+            // parameters without a value, like `utm_source`, get one
+            let name = pair.split_once('=').map_or(pair, |(name, _)| name);
+            if parameters.iter().any(|p| p == pair) {
+                result.push_str(name);
+                result.push_str("=REDACTED");
+            } else {
+                result.push_str(pair);
+            }
+        }
+    }
+    result
+}
+
+/// The ranges of the contents of the double quoted fields of `line`, in which `"` is escaped
+/// as `\"`.
+fn quoted_fields(line: &str) -> impl Iterator<Item = Range<usize>> + '_ {
+    let mut rest = 0;
+    core::iter::from_fn(move || {
+        let start = rest + line[rest..].find('"')? + 1;
+        let mut escaped = false;
+        for (i, c) in line[start..].char_indices() {
+            match c {
+                '\\' if !escaped => escaped = true,
+                '"' if !escaped => {
+                    rest = start + i + 1;
+                    return Some(start..start + i);
+                }
+                _ => escaped = false,
+            }
+        }
+        None
+    })
+}
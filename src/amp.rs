@@ -0,0 +1,63 @@
+//! Unwrapping of AMP cache and viewer URLs, which contain the publisher's URL in their path,
+//! see [`UrlCleaner::unwrap_amp`](crate::UrlCleaner::unwrap_amp).
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use url::Url;
+
+/// The query parameters that AMP caches and viewers add.
+const AMP_PARAMETERS: &[&str] = &["amp_gsa", "amp_js_v", "usqp"];
+
+/// The publisher's URL of an AMP cache or viewer URL, like
+/// `https://example-com.cdn.ampproject.org/c/s/example.com/a` or
+/// `https://www.google.com/amp/s/example.com/a`.
+pub(crate) fn unwrap(url: &str) -> Option<String> {
+    let parsed = Url::parse(url).ok()?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return None;
+    }
+    let host = parsed.host_str()?;
+    let path = parsed.path();
+    let wrapped = if host.ends_with(".cdn.ampproject.org") || host.ends_with(".bing-amp.com") {
+        // content, viewer, image and resource URLs
+        ["/c/", "/v/", "/i/", "/r/"]
+            .iter()
+            .find_map(|prefix| path.strip_prefix(prefix))?
+    } else if is_google(host) {
+        path.strip_prefix("/amp/")?
+    } else {
+        return None;
+    };
+    let (scheme, rest) = match wrapped.strip_prefix("s/") {
+        Some(rest) => ("https://", rest),
+        None => ("http://", wrapped),
+    };
+    let publisher_host = rest.split('/').next().unwrap_or_default();
+    if publisher_host.is_empty() || !publisher_host.contains('.') {
+        return None;
+    }
+    let mut target = Url::parse(&[scheme, rest].concat()).ok()?;
+    let query: Option<String> = parsed.query().map(|query| {
+        query
+            .split('&')
+            .filter(|pair| {
+                let name = pair.split_once('=').map_or(*pair, |(name, _)| name);
+                !AMP_PARAMETERS.contains(&name)
+            })
+            .collect::<Vec<_>>()
+            .join("&")
+    });
+    target.set_query(query.as_deref().filter(|query| !query.is_empty()));
+    target.set_fragment(parsed.fragment());
+    Some(target.into())
+}
+
+/// Whether `host` is Google's, like `www.google.com` or `google.co.uk`.
+fn is_google(host: &str) -> bool {
+    let host = host.strip_prefix("www.").unwrap_or(host);
+    host.strip_prefix("google.").is_some_and(|tld| {
+        tld.split('.')
+            .all(|label| !label.is_empty() && label.len() <= 3)
+    })
+}
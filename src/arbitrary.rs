@@ -0,0 +1,212 @@
+//! [`Arbitrary`] implementations for structure-aware fuzzing of the cleaner and of
+//! loading custom rules.
+//!
+//! The targets in `fuzz/` use them with [`cargo fuzz`](https://github.com/rust-fuzz/cargo-fuzz),
+//! like `cargo fuzz run custom_rules`.
+//!
+//! ```
+//! # use arbitrary::{Arbitrary, Unstructured};
+//! # use clearurls::arbitrary::{ArbitraryRules, ArbitraryUrl};
+//! # use clearurls::UrlCleaner;
+//! let data = [7; 256];
+//! let mut u = Unstructured::new(&data);
+//! let rules = ArbitraryRules::arbitrary(&mut u).unwrap();
+//! let url = ArbitraryUrl::arbitrary(&mut u).unwrap();
+//! if let Ok(cleaner) = UrlCleaner::from_rules_str(&rules.to_json()) {
+//!     let _ = cleaner.clear_url(&url.0);
+//! }
+//! ```
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use ::arbitrary::{Arbitrary, Result, Unstructured};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::ser::SerializeMap;
+use serde::{Serialize, Serializer};
+
+/// Parameter names that are often removed, or contain the target of a redirection.
+const PARAMETERS: &[&str] = &[
+    "utm_source",
+    "utm_medium",
+    "fbclid",
+    "gclid",
+    "ref",
+    "tag",
+    "id",
+    "q",
+    "url",
+    "u",
+    "to",
+];
+
+const SCHEMES: &[&str] = &[
+    "https",
+    "http",
+    "HTTPS",
+    "ftp",
+    "javascript",
+    "data",
+    "mailto",
+];
+
+const HOSTS: &[&str] = &[
+    "example.com",
+    "www.example.com",
+    "www.google.com",
+    "www.amazon.com",
+    "l.facebook.com",
+    "xn--bcher-kva.example",
+    "bücher.example",
+    "127.0.0.1",
+    "[::1]",
+    "user:password@example.com",
+];
+
+/// How deep redirection targets are nested in [`ArbitraryUrl`]s.
+const MAX_DEPTH: usize = 3;
+
+/// Rules in the JSON format of the [ClearURLs browser extension](https://github.com/ClearURLs/Addon),
+/// see [`ArbitraryRules::to_json`].
+#[derive(Debug, Clone, Default, Arbitrary)]
+pub struct ArbitraryRules {
+    /// The providers with their names, in their order
+    pub providers: Vec<(String, ArbitraryProvider)>,
+}
+
+impl ArbitraryRules {
+    /// The rules as a JSON string, for [`UrlCleaner::from_rules_str`][crate::UrlCleaner::from_rules_str].
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        // serializing strings and booleans can't fail
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+impl Serialize for ArbitraryRules {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        struct Providers<'a>(&'a [(String, ArbitraryProvider)]);
+
+        impl Serialize for Providers<'_> {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_map(self.0.iter().map(|(name, p)| (name, p)))
+            }
+        }
+
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry("providers", &Providers(&self.providers))?;
+        map.end()
+    }
+}
+
+/// A provider of [`ArbitraryRules`].
+#[derive(Debug, Clone, Default, Arbitrary, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[allow(missing_docs)]
+pub struct ArbitraryProvider {
+    pub url_pattern: ArbitraryPattern,
+    pub host_only: bool,
+    pub rules: Vec<ArbitraryPattern>,
+    pub raw_rules: Vec<ArbitraryPattern>,
+    pub referral_marketing: Vec<ArbitraryPattern>,
+    pub exceptions: Vec<ArbitraryPattern>,
+    pub redirections: Vec<ArbitraryPattern>,
+    pub base64_redirections: Vec<ArbitraryPattern>,
+}
+
+/// A pattern of an [`ArbitraryProvider`].
+///
+/// Most are valid, like the names of tracking parameters, escaped strings, or redirections
+/// with a capture group, but some are arbitrary strings that may not even compile.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct ArbitraryPattern(pub String);
+
+impl<'a> Arbitrary<'a> for ArbitraryPattern {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let pattern = match u.int_in_range(0..=4)? {
+            0 => String::from(*u.choose(PARAMETERS)?),
+            1 => format!("{}[a-z_]*", u.choose(PARAMETERS)?),
+            2 => regex::escape(u.arbitrary()?),
+            3 => format!(r"^https?://[^/]*/.*?[?&]{}=([^&]*)", u.choose(PARAMETERS)?),
+            _ => u.arbitrary()?,
+        };
+        Ok(Self(pattern))
+    }
+}
+
+/// Something like a URL, to be cleaned.
+///
+/// It has a scheme, host, path, query and fragment that are often realistic, with tracking
+/// parameters and percent-encoded or base64-encoded URLs in parameters, but any part may
+/// be arbitrary or missing.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArbitraryUrl(pub String);
+
+impl<'a> Arbitrary<'a> for ArbitraryUrl {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        url(u, 0).map(Self)
+    }
+}
+
+fn url(u: &mut Unstructured<'_>, depth: usize) -> Result<String> {
+    let mut url = String::new();
+    if u.ratio(9, 10)? {
+        url.push_str(choose_or_arbitrary(u, SCHEMES)?);
+        url.push_str("://");
+    }
+    url.push_str(choose_or_arbitrary(u, HOSTS)?);
+    for _ in 0..u.int_in_range(0..=3)? {
+        url.push('/');
+        url.push_str(u.arbitrary()?);
+    }
+    let query = parameters(u, depth)?;
+    if !query.is_empty() {
+        url.push('?');
+        url.push_str(&query);
+    }
+    if u.ratio(1, 4)? {
+        url.push('#');
+        if depth < MAX_DEPTH && u.arbitrary()? {
+            url.push_str(&self::url(u, depth + 1)?);
+        } else {
+            url.push_str(&parameters(u, depth)?);
+        }
+    }
+    Ok(url)
+}
+
+/// Parameters like `a=1&b=2`, where the values may be encoded URLs.
+fn parameters(u: &mut Unstructured<'_>, depth: usize) -> Result<String> {
+    let mut parameters = Vec::new();
+    for _ in 0..u.int_in_range(0..=4)? {
+        let name = choose_or_arbitrary(u, PARAMETERS)?;
+        let value = if depth < MAX_DEPTH && u.ratio(1, 4)? {
+            let mut target = url(u, depth + 1)?;
+            match u.int_in_range(0..=3)? {
+                0 => target = URL_SAFE_NO_PAD.encode(target),
+                times => {
+                    for _ in 0..times {
+                        target = utf8_percent_encode(&target, NON_ALPHANUMERIC).to_string();
+                    }
+                }
+            }
+            target
+        } else {
+            u.arbitrary()?
+        };
+        parameters.push(format!("{name}={value}"));
+    }
+    Ok(parameters.join("&"))
+}
+
+fn choose_or_arbitrary<'a>(u: &mut Unstructured<'a>, choices: &[&'static str]) -> Result<&'a str> {
+    if u.ratio(7, 8)? {
+        u.choose(choices).copied()
+    } else {
+        u.arbitrary()
+    }
+}
@@ -0,0 +1,177 @@
+//! An append-only audit log of the URLs that a [`UrlCleaner`] changed,
+//! see [`UrlCleaner::audit_log`].
+//!
+//! ```
+//! # use clearurls::audit::AuditLog;
+//! # use clearurls::UrlCleaner;
+//! # fn main() -> Result<(), clearurls::Error> {
+//! let log = std::fs::OpenOptions::new()
+//!     .create(true)
+//!     .append(true)
+//!     .open(std::env::temp_dir().join("clearurls-audit.jsonl"))?;
+//! let cleaner = UrlCleaner::from_embedded_rules()?.audit_log(AuditLog::new(log));
+//! cleaner.clear_url("https://example.com/?utm_source=x")?;
+//! # Ok(())
+//! # }
+//! ```
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{Debug, Formatter, Write as _};
+use std::io::{self, Write};
+use std::sync::{Mutex, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use hmac::{Hmac, KeyInit, Mac};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::{Error, Report, UrlCleaner};
+
+type Sink = dyn Fn(&AuditRecord<'_>) -> io::Result<()> + Send + Sync;
+
+impl UrlCleaner {
+    /// Record each URL that this cleaner changes in `log`.
+    ///
+    /// If the record can't be written, cleaning fails with [`Error::AuditLog`], so no URL
+    /// is changed without evidence.
+    #[must_use]
+    pub fn audit_log(mut self, log: AuditLog) -> Self {
+        self.config.audit_log = Some(log);
+        self
+    }
+}
+
+/// Where [`AuditRecord`]s are written to, see [`UrlCleaner::audit_log`].
+pub struct AuditLog {
+    sink: Box<Sink>,
+    key: Option<Vec<u8>>,
+}
+
+impl AuditLog {
+    /// Write each record to `writer` as a line of JSON, and flush it.
+    pub fn new<W: Write + Send + 'static>(writer: W) -> Self {
+        let writer = Mutex::new(writer);
+        Self::from_fn(move |record| {
+            let mut line = record.to_json();
+            line.push('\n');
+            let mut writer = writer.lock().unwrap_or_else(PoisonError::into_inner);
+            writer.write_all(line.as_bytes())?;
+            writer.flush()
+        })
+    }
+
+    /// Pass each record to `callback`.
+    pub fn from_fn<F>(callback: F) -> Self
+    where
+        F: Fn(&AuditRecord<'_>) -> io::Result<()> + Send + Sync + 'static,
+    {
+        Self {
+            sink: Box::new(callback),
+            key: None,
+        }
+    }
+
+    /// Hash the URLs with HMAC-SHA256 and `key` instead of SHA-256, so that the hashes of
+    /// known URLs can't be recognized without the key.
+    #[must_use]
+    pub fn key(mut self, key: &[u8]) -> Self {
+        self.key = Some(key.to_vec());
+        self
+    }
+
+    pub(crate) fn record(&self, input: &str, report: &Report<'_>) -> Result<(), Error> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |t| u64::try_from(t.as_millis()).unwrap_or(u64::MAX));
+        let record = AuditRecord {
+            timestamp,
+            input_hash: self.hash(input),
+            providers: &report.providers,
+            removed_parameters: report
+                .removed_parameters
+                .iter()
+                .map(|p| p.split_once('=').map_or(p.as_str(), |(name, _)| name))
+                .collect(),
+            redirections: report.redirections.len(),
+            output_hash: self.hash(&report.url),
+        };
+        (self.sink)(&record).map_err(Error::AuditLog)
+    }
+
+    /// The hash of `url` in hex, see [`Self::key`].
+    fn hash(&self, url: &str) -> String {
+        let digest: [u8; 32] = match &self.key {
+            Some(key) => Hmac::<Sha256>::new_from_slice(key)
+                .expect("HMAC accepts keys of any length")
+                .chain_update(url.as_bytes())
+                .finalize()
+                .into_bytes()
+                .into(),
+            None => Sha256::digest(url.as_bytes()).into(),
+        };
+        digest.iter().fold(String::with_capacity(64), |mut hex, b| {
+            let _ = write!(hex, "{b:02x}");
+            hex
+        })
+    }
+}
+
+impl Debug for AuditLog {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("AuditLog").finish_non_exhaustive()
+    }
+}
+
+/// What a [`UrlCleaner`] changed about a URL.
+///
+/// The URLs are only recorded as hashes, and the removed parameters by their names, so the
+/// log can be kept without keeping what users visited. Without a [key][AuditLog::key], the
+/// hash of a known URL can be recognized by hashing it.
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
+pub struct AuditRecord<'a> {
+    /// When the URL was cleaned, in milliseconds since the Unix epoch
+    pub timestamp: u64,
+    /// The hash of the URL before cleaning, in hex, see [`AuditLog::key`]
+    pub input_hash: String,
+    /// The names of the providers that matched, see [`Report::providers`]
+    pub providers: &'a [String],
+    /// The names of the parameters that were removed, without their values, see
+    /// [`Report::removed_parameters`]
+    pub removed_parameters: Vec<&'a str>,
+    /// How many redirections were followed
+    pub redirections: usize,
+    /// The hash of the cleaned URL, in hex, see [`AuditLog::key`]
+    pub output_hash: String,
+}
+
+impl AuditRecord<'_> {
+    /// The record as a single line of JSON.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AuditLog;
+
+    /// The URLs of the public API can't be the messages of reference test vectors.
+    #[test]
+    fn hmac_test_vectors() {
+        // test cases 2 and 6 of RFC 4231
+        let log = AuditLog::from_fn(|_| Ok(())).key(b"Jefe");
+        assert_eq!(
+            log.hash("what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        let log = AuditLog::from_fn(|_| Ok(())).key(&[0xaa; 131]);
+        assert_eq!(
+            log.hash("Test Using Larger Than Block-Size Key - Hash Key First"),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+    }
+}
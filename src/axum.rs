@@ -0,0 +1,54 @@
+//! An [`axum`] extractor of [`CleanedUrl`]s.
+//!
+//! The `url` field of a request is taken from the query string for `GET` and `HEAD`
+//! requests, and from the body otherwise, which is JSON if the `Content-Type` says so, or
+//! else a form. It's cleaned with the [global cleaner][crate::UrlCleaner::global]. Requests
+//! without the field, or with a URL that can't be cleaned, are rejected with the response of
+//! [`Form`] or [`Json`], like `400 Bad Request`.
+//!
+//! ```
+//! # use clearurls::CleanedUrl;
+//! async fn submit(url: CleanedUrl) -> String {
+//!     format!("submitted {url}")
+//! }
+//!
+//! let app: axum::Router = axum::Router::new().route("/submit", axum::routing::post(submit));
+//! ```
+
+use ::axum::extract::{FromRequest, Request};
+use ::axum::http::header::CONTENT_TYPE;
+use ::axum::http::Method;
+use ::axum::response::{IntoResponse, Response};
+use ::axum::{Form, Json};
+use serde::Deserialize;
+
+use crate::CleanedUrl;
+
+#[derive(Deserialize)]
+struct Field {
+    url: CleanedUrl,
+}
+
+impl<S: Send + Sync> FromRequest<S> for CleanedUrl {
+    type Rejection = Response;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Response> {
+        let is_json = request
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.contains("json"));
+        let field = if is_json && !matches!(*request.method(), Method::GET | Method::HEAD) {
+            Json::<Field>::from_request(request, state)
+                .await
+                .map_err(IntoResponse::into_response)?
+                .0
+        } else {
+            Form::<Field>::from_request(request, state)
+                .await
+                .map_err(IntoResponse::into_response)?
+                .0
+        };
+        Ok(field.url)
+    }
+}
@@ -0,0 +1,7 @@
+//! Generate the Swift and Kotlin bindings of the `uniffi` feature, like
+//! `cargo run --features uniffi-bindgen --bin uniffi-bindgen -- generate --library
+//! target/release/libclearurls.so --language kotlin --out-dir out`.
+
+fn main() {
+    uniffi::uniffi_bindgen_main();
+}
@@ -0,0 +1,257 @@
+//! Cleaning the URLs of exported bookmarks, in the Netscape HTML format that all browsers
+//! export, and in the JSON of Chrome's `Bookmarks` file and Firefox's backups. [`clean_file`]
+//! cleans such a file of a browser profile in place.
+//!
+//! ```
+//! # use clearurls::UrlCleaner;
+//! # fn main() -> Result<(), clearurls::Error> {
+//! let cleaner = UrlCleaner::from_embedded_rules()?;
+//! let html = "<DT><A HREF=\"https://example.com/?utm_source=x\" ADD_DATE=\"1\">Example</A>";
+//! assert_eq!(
+//!     clearurls::bookmarks::clean_html(&cleaner, html),
+//!     "<DT><A HREF=\"https://example.com/\" ADD_DATE=\"1\">Example</A>",
+//! );
+//! # Ok(())
+//! # }
+//! ```
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use ::lol_html::html_content::Element;
+use ::lol_html::{element, rewrite_str, RewriteStrSettings};
+use serde::Serialize;
+use serde_json::ser::{CharEscape, Formatter, Serializer};
+use serde_json::Value;
+
+use crate::lol_html::clean_value;
+use crate::UrlCleaner;
+
+/// Clean the links of a bookmarks export in the Netscape HTML format, and keep everything
+/// else, like folders, dates and icons, as it is.
+#[must_use]
+pub fn clean_html<'a>(cleaner: &UrlCleaner, html: &'a str) -> Cow<'a, str> {
+    let settings = RewriteStrSettings::new().append_element_content_handler(element!(
+        "a[href]",
+        |el: &mut Element<'_, '_>| {
+            if let Some(href) = el.get_attribute("href") {
+                if let Some(result) = clean_value(cleaner, &href) {
+                    el.set_attribute("href", &result)?;
+                }
+            }
+            Ok(())
+        }
+    ));
+    match rewrite_str(html, settings) {
+        Ok(result) if result != html => Cow::Owned(result),
+        _ => Cow::Borrowed(html),
+    }
+}
+
+/// Clean the URLs of bookmarks in the JSON of Chrome's `Bookmarks` file or a Firefox backup,
+/// and return how many were changed.
+///
+/// Chrome's bookmarks are the `url`s of nodes of type `url`, and Firefox's are the `uri`s of
+/// nodes of type `text/x-moz-place`. If a URL changes, the `checksum` of Chrome's file is
+/// removed, so that Chrome computes it again instead of rejecting the file.
+pub fn clean_json(cleaner: &UrlCleaner, bookmarks: &mut Value) -> usize {
+    let changed = clean_node(cleaner, bookmarks);
+    if changed > 0 {
+        if let Some(bookmarks) = bookmarks.as_object_mut() {
+            bookmarks.shift_remove("checksum");
+        }
+    }
+    changed
+}
+
+/// Clean the JSON of Chrome's `Bookmarks` file or a Firefox backup with [`clean_json`], and
+/// write it again like the browser does, or return it as it is if no URL changed.
+///
+/// Chrome's file is written in the layout of Chrome's pretty printer, with its line endings,
+/// and Firefox's backup compactly, like `JSON.stringify`. Whitespace around the JSON is kept.
+///
+/// # Errors
+/// If the bookmarks aren't valid JSON.
+pub fn clean_json_str<'a>(cleaner: &UrlCleaner, json: &'a str) -> serde_json::Result<Cow<'a, str>> {
+    let mut bookmarks: Value = serde_json::from_str(json)?;
+    if clean_json(cleaner, &mut bookmarks) == 0 {
+        return Ok(Cow::Borrowed(json));
+    }
+    let mut output = Vec::with_capacity(json.len());
+    if bookmarks.get("roots").is_some() {
+        let newline = if json.contains("\r\n") { "\r\n" } else { "\n" };
+        let formatter = ChromeFormatter { depth: 0, newline };
+        bookmarks.serialize(&mut Serializer::with_formatter(&mut output, formatter))?;
+    } else {
+        serde_json::to_writer(&mut output, &bookmarks)?;
+    }
+    let start = json.len() - json.trim_start().len();
+    let end = json.trim_end().len();
+    let mut result = String::with_capacity(output.len() + start + json.len() - end);
+    result.push_str(&json[..start]);
+    // the serializer only writes valid UTF-8
+    result.push_str(&String::from_utf8_lossy(&output));
+    result.push_str(&json[end..]);
+    Ok(Cow::Owned(result))
+}
+
+/// Clean a bookmarks file in place, an HTML export with [`clean_html`] or a JSON file with
+/// [`clean_json_str`], and return whether it changed.
+///
+/// The cleaned file is written next to it first, with `.tmp` appended to its name, and then
+/// replaces it. The browser should be closed, so that it doesn't overwrite the file.
+///
+/// # Errors
+/// If the file can't be read or written, or it is JSON and isn't valid.
+pub fn clean_file(cleaner: &UrlCleaner, path: impl AsRef<Path>) -> io::Result<bool> {
+    let path = path.as_ref();
+    let content = std::fs::read_to_string(path)?;
+    let result = if content.trim_start().starts_with('{') {
+        clean_json_str(cleaner, &content)?
+    } else {
+        clean_html(cleaner, &content)
+    };
+    match result {
+        Cow::Owned(result) => {
+            // write to another file first, so that the bookmarks are never partially written
+            let mut tmp = std::ffi::OsString::from(path);
+            tmp.push(".tmp");
+            let tmp = PathBuf::from(tmp);
+            std::fs::write(&tmp, result)
+                .and_then(|()| std::fs::rename(&tmp, path))
+                .inspect_err(|_| {
+                    std::fs::remove_file(&tmp).ok();
+                })?;
+            Ok(true)
+        }
+        Cow::Borrowed(_) => Ok(false),
+    }
+}
+
+/// Writes JSON like Chrome's `JSONWriter` with pretty printing: objects with a member per line
+/// indented by three spaces, arrays on the line they start, like `[ {`, and `<` escaped.
+struct ChromeFormatter {
+    depth: usize,
+    newline: &'static str,
+}
+
+impl ChromeFormatter {
+    fn write_line<W: ?Sized + Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(self.newline.as_bytes())?;
+        for _ in 0..self.depth {
+            writer.write_all(b"   ")?;
+        }
+        Ok(())
+    }
+}
+
+impl Formatter for ChromeFormatter {
+    fn begin_array<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(b"[ ")
+    }
+
+    fn end_array<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(b" ]")
+    }
+
+    fn begin_array_value<W: ?Sized + Write>(
+        &mut self,
+        writer: &mut W,
+        first: bool,
+    ) -> io::Result<()> {
+        if first {
+            Ok(())
+        } else {
+            writer.write_all(b", ")
+        }
+    }
+
+    fn begin_object<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.depth += 1;
+        writer.write_all(b"{")
+    }
+
+    fn end_object<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.depth -= 1;
+        self.write_line(writer)?;
+        writer.write_all(b"}")
+    }
+
+    fn begin_object_key<W: ?Sized + Write>(
+        &mut self,
+        writer: &mut W,
+        first: bool,
+    ) -> io::Result<()> {
+        if !first {
+            writer.write_all(b",")?;
+        }
+        self.write_line(writer)
+    }
+
+    fn begin_object_value<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(b": ")
+    }
+
+    fn write_string_fragment<W: ?Sized + Write>(
+        &mut self,
+        writer: &mut W,
+        fragment: &str,
+    ) -> io::Result<()> {
+        let mut copied = 0;
+        for (i, c) in fragment.char_indices() {
+            let escape = match c {
+                '<' => "\\u003C",
+                '\u{2028}' => "\\u2028",
+                '\u{2029}' => "\\u2029",
+                _ => continue,
+            };
+            writer.write_all(&fragment.as_bytes()[copied..i])?;
+            writer.write_all(escape.as_bytes())?;
+            copied = i + c.len_utf8();
+        }
+        writer.write_all(&fragment.as_bytes()[copied..])
+    }
+
+    fn write_char_escape<W: ?Sized + Write>(
+        &mut self,
+        writer: &mut W,
+        escape: CharEscape,
+    ) -> io::Result<()> {
+        match escape {
+            CharEscape::AsciiControl(byte) => write!(writer, "\\u{byte:04X}"),
+            escape => serde_json::ser::CompactFormatter.write_char_escape(writer, escape),
+        }
+    }
+}
+
+fn clean_node(cleaner: &UrlCleaner, node: &mut Value) -> usize {
+    match node {
+        Value::Array(nodes) => nodes.iter_mut().map(|node| clean_node(cleaner, node)).sum(),
+        Value::Object(node) => {
+            let field = match node.get("type").and_then(Value::as_str) {
+                Some("url") => Some("url"),
+                Some("text/x-moz-place") => Some("uri"),
+                _ => None,
+            };
+            let mut changed = 0;
+            if let Some(Value::String(url)) = field.and_then(|field| node.get_mut(field)) {
+                if let Ok(result) = cleaner.clear_url(url) {
+                    if result != *url {
+                        *url = result.into_owned();
+                        changed += 1;
+                    }
+                }
+            }
+            // folders are in `roots` and `children`
+            changed
+                + node
+                    .values_mut()
+                    .map(|node| clean_node(cleaner, node))
+                    .sum::<usize>()
+        }
+        _ => 0,
+    }
+}
@@ -0,0 +1,133 @@
+//! Known URL shorteners and link redirectors, to recognize their links without requesting
+//! them, like to warn before following one.
+//!
+//! ```
+//! # use clearurls::UrlCleaner;
+//! # fn main() -> Result<(), clearurls::Error> {
+//! assert!(clearurls::catalog::is_shortener("https://bit.ly/abc"));
+//! let cleaner = UrlCleaner::from_embedded_rules()?;
+//! # #[cfg(not(feature = "strip-embedded-redirections"))]
+//! assert!(cleaner.is_redirector("https://www.google.com/url?q=https%3A%2F%2Fexample.com%2F"));
+//! assert!(!cleaner.is_redirector("https://example.com/"));
+//! # Ok(())
+//! # }
+//! ```
+
+use url::Url;
+
+use crate::rules::url_for_matching;
+use crate::UrlCleaner;
+
+/// The hosts of URL shorteners, whose destination can only be found by requesting their links.
+pub const SHORTENERS: &[&str] = &[
+    "adf.ly",
+    "aka.ms",
+    "amzn.eu",
+    "amzn.to",
+    "apple.co",
+    "bit.do",
+    "bit.ly",
+    "bitly.com",
+    "bl.ink",
+    "buff.ly",
+    "clck.ru",
+    "cutt.ly",
+    "db.tt",
+    "dlvr.it",
+    "fb.me",
+    "forms.gle",
+    "g.co",
+    "goo.gl",
+    "ift.tt",
+    "is.gd",
+    "j.mp",
+    "lnkd.in",
+    "mzl.la",
+    "nyti.ms",
+    "ow.ly",
+    "rb.gy",
+    "rebrand.ly",
+    "redd.it",
+    "s.id",
+    "shorturl.at",
+    "snip.ly",
+    "spoti.fi",
+    "t.co",
+    "t.ly",
+    "tiny.cc",
+    "tinyurl.com",
+    "trib.al",
+    "urlz.fr",
+    "v.gd",
+    "wapo.st",
+    "wp.me",
+];
+
+/// The hosts of link redirectors that aren't in the rules, like the ones of email security
+/// gateways. Their links contain the destination, or lead to it through a warning page.
+pub const REDIRECTORS: &[&str] = &[
+    "away.vk.com",
+    "click.linksynergy.com",
+    "deref-gmx.net",
+    "exit.sc",
+    "go.redirectingat.com",
+    "href.li",
+    "l.facebook.com",
+    "l.instagram.com",
+    "l.messenger.com",
+    "link.zhihu.com",
+    "linkprotect.cudasvc.com",
+    "lm.facebook.com",
+    "out.reddit.com",
+    "safelinks.protection.outlook.com",
+    "slack-redir.net",
+    "t.umblr.com",
+    "urldefense.com",
+    "urldefense.proofpoint.com",
+];
+
+/// Whether `url` is an `http` or `https` link of one of the [`SHORTENERS`], or a subdomain of
+/// one.
+#[must_use]
+pub fn is_shortener(url: &str) -> bool {
+    has_host(url, SHORTENERS)
+}
+
+impl UrlCleaner {
+    /// Whether `url` is a link of a redirector, whose destination is in the URL, or through
+    /// a warning page: one of the [`REDIRECTORS`], or a URL that a redirection pattern of the
+    /// rules matches.
+    ///
+    /// Shorteners aren't redirectors, see [`is_shortener`].
+    #[must_use]
+    pub fn is_redirector(&self, url: &str) -> bool {
+        if has_host(url, REDIRECTORS) {
+            return true;
+        }
+        let matching_url = url_for_matching(url, &self.config);
+        self.rules
+            .providers
+            .iter()
+            .any(|(_, p)| p.match_url(&matching_url) && p.match_redirection(&matching_url))
+    }
+}
+
+/// Whether `url` is an `http` or `https` URL whose host is one of `hosts`, or a subdomain of
+/// one. `hosts` are compared case-insensitively.
+pub(crate) fn has_host<S: AsRef<str>>(url: &str, hosts: &[S]) -> bool {
+    let Ok(url) = Url::parse(url) else {
+        return false;
+    };
+    let Some(host) = url.host_str() else {
+        return false;
+    };
+    // `Url` lowercases the host of http(s) URLs
+    matches!(url.scheme(), "http" | "https")
+        && hosts.iter().any(|s| {
+            let s = s.as_ref();
+            host.len().checked_sub(s.len()).is_some_and(|i| {
+                host.get(i..).is_some_and(|end| end.eq_ignore_ascii_case(s))
+                    && (i == 0 || host[..i].ends_with('.'))
+            })
+        })
+}
@@ -0,0 +1,159 @@
+use core::fmt;
+
+use crate::Report;
+
+/// What kind of tracking a query or fragment parameter is for, by its name, see
+/// [`Report::categorized_parameters`].
+///
+/// ```
+/// # use clearurls::Category;
+/// assert_eq!(Category::of("utm_source"), Category::Analytics);
+/// assert_eq!(Category::of("gclid"), Category::Advertising);
+/// assert_eq!(Category::of("ref"), Category::Referral);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Category {
+    /// Campaign and web analytics, like `utm_source` or Matomo's `pk_campaign`
+    Analytics,
+    /// Ad click identifiers, like Google's `gclid` or Facebook's `fbclid`
+    Advertising,
+    /// Affiliate and referral programs, like Amazon's `tag` or `ref`
+    Referral,
+    /// Session identifiers, like `sessionid` or `jsessionid`
+    Session,
+    /// A/B tests and experiments, like `variant` or `optimizely_x`
+    Experiment,
+    /// Parameters that aren't in any of the other categories
+    #[default]
+    Other,
+}
+
+/// Names of parameters and their categories, compared case-insensitively. A name ending in `*`
+/// is a prefix.
+const TAXONOMY: &[(&str, Category)] = &[
+    ("utm_*", Category::Analytics),
+    ("_ga", Category::Analytics),
+    ("_gl", Category::Analytics),
+    ("_hsenc", Category::Analytics),
+    ("_hsmi", Category::Analytics),
+    ("__hs*", Category::Analytics),
+    ("_openstat", Category::Analytics),
+    ("ga_*", Category::Analytics),
+    ("hsa_*", Category::Analytics),
+    ("icid", Category::Analytics),
+    ("matomo_*", Category::Analytics),
+    ("mc_cid", Category::Analytics),
+    ("mc_eid", Category::Analytics),
+    ("mkt_tok", Category::Analytics),
+    ("mtm_*", Category::Analytics),
+    ("oly_*", Category::Analytics),
+    ("piwik_*", Category::Analytics),
+    ("pk_*", Category::Analytics),
+    ("s_cid", Category::Analytics),
+    ("vero_*", Category::Analytics),
+    ("wt_*", Category::Analytics),
+    ("wt.*", Category::Analytics),
+    ("dclid", Category::Advertising),
+    ("epik", Category::Advertising),
+    ("fbclid", Category::Advertising),
+    ("gbraid", Category::Advertising),
+    ("gclid", Category::Advertising),
+    ("gclsrc", Category::Advertising),
+    ("igshid", Category::Advertising),
+    ("li_fat_id", Category::Advertising),
+    ("msclkid", Category::Advertising),
+    ("obclid", Category::Advertising),
+    ("sccid", Category::Advertising),
+    ("ttclid", Category::Advertising),
+    ("twclid", Category::Advertising),
+    ("wbraid", Category::Advertising),
+    ("yclid", Category::Advertising),
+    ("aff", Category::Referral),
+    ("aff_*", Category::Referral),
+    ("affid", Category::Referral),
+    ("affiliate*", Category::Referral),
+    ("ascsubtag", Category::Referral),
+    ("irclickid", Category::Referral),
+    ("irgwc", Category::Referral),
+    ("linkcode", Category::Referral),
+    ("ranmid", Category::Referral),
+    ("raneaid", Category::Referral),
+    ("ransiteid", Category::Referral),
+    ("ref", Category::Referral),
+    ("ref_*", Category::Referral),
+    ("referrer", Category::Referral),
+    ("tag", Category::Referral),
+    ("aspsessionid*", Category::Session),
+    ("jsessionid", Category::Session),
+    ("phpsessid", Category::Session),
+    ("session_id", Category::Session),
+    ("sessionid", Category::Session),
+    ("sid", Category::Session),
+    ("ab", Category::Experiment),
+    ("ab_*", Category::Experiment),
+    ("abtest", Category::Experiment),
+    ("exp_*", Category::Experiment),
+    ("experiment*", Category::Experiment),
+    ("optimizely_*", Category::Experiment),
+    ("variant", Category::Experiment),
+    ("vwo_*", Category::Experiment),
+];
+
+impl Category {
+    /// The category of a parameter by its name.
+    #[must_use]
+    pub fn of(name: &str) -> Self {
+        TAXONOMY
+            .iter()
+            .find(|(pattern, _)| match pattern.strip_suffix('*') {
+                Some(prefix) => name
+                    .get(..prefix.len())
+                    .is_some_and(|start| start.eq_ignore_ascii_case(prefix)),
+                None => name.eq_ignore_ascii_case(pattern),
+            })
+            .map_or(Self::Other, |(_, category)| *category)
+    }
+
+    /// The name of the category, like `analytics`.
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Analytics => "analytics",
+            Self::Advertising => "advertising",
+            Self::Referral => "referral",
+            Self::Session => "session",
+            Self::Experiment => "experiment",
+            Self::Other => "other",
+        }
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Report<'_> {
+    /// The [removed parameters][Self::removed_parameters], each with its [`Category`].
+    ///
+    /// ```
+    /// # use clearurls::{Category, UrlCleaner};
+    /// # fn main() -> Result<(), clearurls::Error> {
+    /// let cleaner = UrlCleaner::from_embedded_rules()?;
+    /// let report = cleaner.clear_url_with_report("https://example.com/?utm_source=x")?;
+    /// let categorized: Vec<_> = report.categorized_parameters().collect();
+    /// assert_eq!(categorized, [("utm_source=x", Category::Analytics)]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn categorized_parameters(&self) -> impl Iterator<Item = (&str, Category)> + '_ {
+        self.removed_parameters.iter().map(|parameter| {
+            let name = parameter
+                .split_once('=')
+                .map_or(&**parameter, |(name, _)| name);
+            (parameter.as_str(), Category::of(name))
+        })
+    }
+}
@@ -0,0 +1,75 @@
+//! Cleaning URLs while deserializing them, with a cleaner shared by the whole program.
+//!
+//! [`CleanedUrl`](crate::CleanedUrl)s are cleaned with it when they are deserialized, and
+//! strings with [`deserialize`].
+//!
+//! ```
+//! # use clearurls::CleanedUrl;
+//! #[derive(serde::Deserialize)]
+//! struct Submission {
+//!     title: String,
+//!     link: CleanedUrl,
+//!     #[serde(deserialize_with = "clearurls::clean_url::deserialize")]
+//!     source: String,
+//! }
+//!
+//! let submission: Submission = serde_json::from_str(
+//!     r#"{
+//!         "title": "Example",
+//!         "link": "https://example.com/a?utm_source=x",
+//!         "source": "https://example.com/b?utm_medium=y"
+//!     }"#,
+//! )
+//! .unwrap();
+//! assert_eq!(submission.link, "https://example.com/a");
+//! assert_eq!(submission.source, "https://example.com/b");
+//! ```
+
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::string::String;
+use std::sync::OnceLock;
+
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer};
+
+use crate::UrlCleaner;
+
+static GLOBAL: OnceLock<UrlCleaner> = OnceLock::new();
+
+impl UrlCleaner {
+    /// The cleaner shared by the whole program, as used by [`deserialize`] and to deserialize
+    /// [`CleanedUrl`](crate::CleanedUrl)s.
+    ///
+    /// It's the one passed to [`UrlCleaner::set_global`], or one with the
+    /// [embedded rules][UrlCleaner::from_embedded_rules] if there was none before first use.
+    ///
+    /// # Panics
+    /// If the embedded rules are invalid, which the tests of this crate check.
+    pub fn global() -> &'static UrlCleaner {
+        GLOBAL.get_or_init(|| {
+            UrlCleaner::from_embedded_rules().expect("the embedded rules are valid")
+        })
+    }
+
+    /// Make `cleaner` the one returned by [`UrlCleaner::global`].
+    ///
+    /// # Errors
+    /// If the global cleaner was already set or used, `cleaner` is returned back.
+    pub fn set_global(cleaner: UrlCleaner) -> Result<(), Box<UrlCleaner>> {
+        GLOBAL.set(cleaner).map_err(Box::new)
+    }
+}
+
+/// Deserialize a string and clean it with the [global cleaner][UrlCleaner::global],
+/// for use with `#[serde(deserialize_with = "clearurls::clean_url::deserialize")]`.
+///
+/// # Errors
+/// If the input is not a string, or the URL can't be cleaned.
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let url = <Cow<'de, str>>::deserialize(deserializer)?;
+    let cleaned = UrlCleaner::global()
+        .clear_url(&url)
+        .map_err(D::Error::custom)?;
+    Ok(cleaned.into_owned())
+}
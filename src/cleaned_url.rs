@@ -0,0 +1,135 @@
+//! Cleaning URLs into a [`CleanedUrl`], a parsed URL whose type says that it was cleaned.
+
+use alloc::string::String;
+use core::fmt::{Display, Formatter};
+use core::ops::Deref;
+
+use serde::{Serialize, Serializer};
+use url::Url;
+
+use crate::{Error, UrlCleaner};
+
+impl UrlCleaner {
+    /// Like [`Self::clear_url`], but parse the result, so the type says it was cleaned.
+    ///
+    /// # Errors
+    /// If an error occurred while cleaning, see [`Self::clear_url`], or the cleaned URL
+    /// can't be parsed, like `Error::UrlSyntax`.
+    pub fn clear_url_parsed(&self, url: &str) -> Result<CleanedUrl, Error> {
+        let cleaned = self.clear_url(url)?;
+        Ok(CleanedUrl(Url::parse(&cleaned)?))
+    }
+}
+
+/// A [`Url`] that was cleaned by a [`UrlCleaner`], see [`UrlCleaner::clear_url_parsed`].
+///
+/// It can't be constructed or changed otherwise, so functions that take a `CleanedUrl` can
+/// rely on it being clean. It derefs to the [`Url`], and is serialized as a string.
+/// With the `std` feature, it's deserialized from a string, which is cleaned with the
+/// [global cleaner][UrlCleaner::global].
+///
+/// ```
+/// # use clearurls::{CleanedUrl, UrlCleaner};
+/// # fn main() -> Result<(), clearurls::Error> {
+/// fn share(url: &CleanedUrl) -> String {
+///     format!("have a look at {url}")
+/// }
+///
+/// let cleaner = UrlCleaner::from_embedded_rules()?;
+/// let url = cleaner.clear_url_parsed("https://example.com/a?utm_source=x")?;
+/// assert_eq!(url.path(), "/a");
+/// assert_eq!(share(&url), "have a look at https://example.com/a");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CleanedUrl(Url);
+
+impl CleanedUrl {
+    /// The cleaned URL.
+    #[must_use]
+    pub fn as_url(&self) -> &Url {
+        &self.0
+    }
+
+    /// The cleaned URL.
+    #[must_use]
+    pub fn into_url(self) -> Url {
+        self.0
+    }
+
+    /// The cleaned URL, serialized.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl Deref for CleanedUrl {
+    type Target = Url;
+
+    fn deref(&self) -> &Url {
+        &self.0
+    }
+}
+
+impl AsRef<Url> for CleanedUrl {
+    fn as_ref(&self) -> &Url {
+        &self.0
+    }
+}
+
+impl AsRef<str> for CleanedUrl {
+    fn as_ref(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl From<CleanedUrl> for Url {
+    fn from(url: CleanedUrl) -> Self {
+        url.0
+    }
+}
+
+impl From<CleanedUrl> for String {
+    fn from(url: CleanedUrl) -> Self {
+        url.0.into()
+    }
+}
+
+impl PartialEq<str> for CleanedUrl {
+    fn eq(&self, other: &str) -> bool {
+        self.0.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for CleanedUrl {
+    fn eq(&self, other: &&str) -> bool {
+        self.0.as_str() == *other
+    }
+}
+
+impl Display for CleanedUrl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.0.as_str())
+    }
+}
+
+impl Serialize for CleanedUrl {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.0.as_str())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'de> serde::Deserialize<'de> for CleanedUrl {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use alloc::borrow::Cow;
+        use serde::de::Error as _;
+
+        let url = <Cow<'de, str>>::deserialize(deserializer)?;
+        UrlCleaner::global()
+            .clear_url_parsed(&url)
+            .map_err(D::Error::custom)
+    }
+}
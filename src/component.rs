@@ -0,0 +1,68 @@
+//! A WebAssembly component with the `cleaner` interface of `wit/clearurls.wit`, for hosts of
+//! the component model like wasmtime.
+//!
+//! Build it with
+//! `cargo rustc --lib --crate-type cdylib --target wasm32-wasip2 --release --features component`.
+//! Until rules are loaded with `load-rules`, the [global cleaner][UrlCleaner::global] is used.
+
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use std::sync::{PoisonError, RwLock};
+
+use crate::UrlCleaner;
+
+mod bindings {
+    #![allow(missing_docs, unsafe_code, clippy::all, clippy::pedantic)]
+
+    wit_bindgen::generate!({
+        path: "wit",
+        world: "clearurls",
+    });
+}
+
+use bindings::exports::clearurls::clearurls::cleaner::{Guest, Redirection, Report};
+
+/// The cleaner with the rules of `load-rules`.
+static LOADED: RwLock<Option<UrlCleaner>> = RwLock::new(None);
+
+struct Component;
+
+bindings::export!(Component with_types_in bindings);
+
+fn with_cleaner<T>(f: impl FnOnce(&UrlCleaner) -> T) -> T {
+    let loaded = LOADED.read().unwrap_or_else(PoisonError::into_inner);
+    f(loaded.as_ref().unwrap_or_else(|| UrlCleaner::global()))
+}
+
+impl Guest for Component {
+    fn load_rules(rules: String) -> Result<(), String> {
+        let cleaner = UrlCleaner::from_rules_str(&rules).map_err(|e| e.to_string())?;
+        *LOADED.write().unwrap_or_else(PoisonError::into_inner) = Some(cleaner);
+        Ok(())
+    }
+
+    fn clean(url: String) -> Result<String, String> {
+        let cleaned = with_cleaner(|cleaner| cleaner.clear_url(&url).map(Cow::into_owned));
+        cleaned.map_err(|e| e.to_string())
+    }
+
+    fn clean_with_report(url: String) -> Result<Report, String> {
+        let report = with_cleaner(|cleaner| cleaner.clear_url_with_report(&url))
+            .map_err(|e| e.to_string())?;
+        Ok(Report {
+            url: report.url.into_owned(),
+            providers: report.providers,
+            removed_parameters: report.removed_parameters,
+            redirections: report
+                .redirections
+                .into_iter()
+                .map(|r| Redirection {
+                    provider: r.provider,
+                    pattern: r.pattern,
+                    decodes: u32::try_from(r.decodes).unwrap_or(u32::MAX),
+                    target: r.target,
+                })
+                .collect(),
+        })
+    }
+}
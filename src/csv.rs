@@ -0,0 +1,258 @@
+//! Cleaning the URLs in columns of CSV or TSV streams, for data pipelines.
+//!
+//! [`ColumnCleaner`] reads one record at a time with the [`csv`](https://docs.rs/csv) crate, so
+//! streams of any size are cleaned with constant memory. Everything but the changed fields is
+//! kept byte for byte.
+//!
+//! ```
+//! # use clearurls::UrlCleaner;
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let cleaner = UrlCleaner::from_embedded_rules()?;
+//! let input = "id,url\n1,\"https://example.com/?a=1,2&utm_source=x\"\n";
+//! let mut output = Vec::new();
+//! clearurls::csv::ColumnCleaner::new(&cleaner)
+//!     .column("url")
+//!     .clean(input.as_bytes(), &mut output)?;
+//! assert_eq!(output, b"id,url\n1,\"https://example.com/?a=1,2\"\n");
+//! # Ok(())
+//! # }
+//! ```
+
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use std::io::{self, Read, Write};
+
+use ::csv::{ByteRecord, ReaderBuilder};
+
+use crate::UrlCleaner;
+
+/// Cleans the URLs in some columns of CSV records.
+///
+/// Fields that aren't valid UTF-8 or can't be cleaned, like empty ones, are left unchanged.
+#[derive(Debug, Clone)]
+pub struct ColumnCleaner<'a> {
+    cleaner: &'a UrlCleaner,
+    names: Vec<String>,
+    indices: Vec<usize>,
+    delimiter: u8,
+    has_headers: bool,
+}
+
+impl<'a> ColumnCleaner<'a> {
+    /// Construct a [`ColumnCleaner`] of comma separated records with a header row, without any
+    /// columns to clean.
+    #[must_use]
+    pub fn new(cleaner: &'a UrlCleaner) -> Self {
+        Self {
+            cleaner,
+            names: Vec::new(),
+            indices: Vec::new(),
+            delimiter: b',',
+            has_headers: true,
+        }
+    }
+
+    /// Clean the column named `name` in the header row.
+    #[must_use]
+    pub fn column(mut self, name: &str) -> Self {
+        self.names.push(name.to_string());
+        self
+    }
+
+    /// Clean the column at `index`, starting at 0.
+    #[must_use]
+    pub fn column_index(mut self, index: usize) -> Self {
+        self.indices.push(index);
+        self
+    }
+
+    /// Separate fields with `delimiter`, like `b'\t'` for TSV. The default is `b','`.
+    #[must_use]
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Whether the first record is a header row, which is never cleaned. The default is `true`.
+    ///
+    /// Without one, columns can only be selected by [index][Self::column_index].
+    #[must_use]
+    pub fn has_headers(mut self, has_headers: bool) -> Self {
+        self.has_headers = has_headers;
+        self
+    }
+
+    /// Read records from `reader`, clean them, write them to `writer`, and return how many
+    /// fields were changed.
+    ///
+    /// Records that didn't change are copied byte for byte, like the header row. In the others,
+    /// only the changed fields are written again, quoted if they were quoted before or need to be.
+    ///
+    /// # Errors
+    /// If a record can't be read or written, its number of fields is different from the
+    /// others, or a [named column][Self::column] isn't in the header row or there is none.
+    pub fn clean<R: Read, W: Write>(&self, reader: R, mut writer: W) -> ::csv::Result<usize> {
+        if !self.has_headers && !self.names.is_empty() {
+            return Err(invalid_input("columns can't be named without a header row").into());
+        }
+        let mut reader = ReaderBuilder::new()
+            .delimiter(self.delimiter)
+            .has_headers(false)
+            .from_reader(Tee::new(reader));
+        let mut columns = self.indices.clone();
+        let mut record = ByteRecord::new();
+        // the fields of the previous record that changed, which is written once it's known
+        // where it ends
+        let mut edits = Vec::new();
+        let mut changed = 0;
+        let mut header = self.has_headers;
+        while reader.read_byte_record(&mut record)? {
+            let start = record.position().map_or(0, ::csv::Position::byte);
+            self.copy(reader.get_mut(), &mut writer, start, &edits)?;
+            edits.clear();
+            if header {
+                header = false;
+                for name in &self.names {
+                    let index = record.iter().position(|field| field == name.as_bytes());
+                    let index = index.ok_or_else(|| {
+                        invalid_input(&alloc::format!("there is no column named {name}"))
+                    })?;
+                    columns.push(index);
+                }
+                continue;
+            }
+            for (i, field) in record.iter().enumerate() {
+                if columns.contains(&i) {
+                    if let Some(result) = self.clean_field(field) {
+                        edits.push((i, result));
+                    }
+                }
+            }
+            changed += edits.len();
+        }
+        let tee = reader.get_mut();
+        let end = tee.start + tee.buffer.len() as u64;
+        self.copy(tee, &mut writer, end, &edits)?;
+        writer.flush()?;
+        Ok(changed)
+    }
+
+    /// Write the input up to `end` to `writer`, with the fields of its record in `edits`
+    /// replaced, and forget it.
+    fn copy<R>(
+        &self,
+        tee: &mut Tee<R>,
+        writer: &mut impl Write,
+        end: u64,
+        edits: &[(usize, String)],
+    ) -> io::Result<()> {
+        let len = usize::try_from(end - tee.start).unwrap_or(usize::MAX);
+        let raw = &tee.buffer[..len.min(tee.buffer.len())];
+        if edits.is_empty() {
+            writer.write_all(raw)?;
+        } else {
+            writer.write_all(&self.replace_fields(raw, edits))?;
+        }
+        tee.buffer.drain(..raw.len());
+        tee.start = end;
+        Ok(())
+    }
+
+    /// The raw `record`, which may start with the line endings before it and ends with its
+    /// own, with the fields in `edits` replaced.
+    fn replace_fields(&self, record: &[u8], edits: &[(usize, String)]) -> Vec<u8> {
+        let start = record
+            .iter()
+            .position(|b| !matches!(b, b'\r' | b'\n'))
+            .unwrap_or(record.len());
+        let mut fields = Vec::new();
+        let mut field_start = start;
+        let mut end = record.len();
+        let mut quoted = false;
+        for (i, &b) in record.iter().enumerate().skip(start) {
+            if b == b'"' {
+                quoted = !quoted;
+            } else if !quoted && b == self.delimiter {
+                fields.push(field_start..i);
+                field_start = i + 1;
+            } else if !quoted && matches!(b, b'\r' | b'\n') {
+                end = i;
+                break;
+            }
+        }
+        fields.push(field_start..end);
+
+        let mut result = record[..start].to_vec();
+        for (i, field) in fields.into_iter().enumerate() {
+            if i > 0 {
+                result.push(self.delimiter);
+            }
+            match edits.iter().find(|(index, _)| *index == i) {
+                Some((_, value)) => {
+                    let raw = &record[field];
+                    self.push_field(&mut result, value, raw.starts_with(b"\""));
+                }
+                None => result.extend_from_slice(&record[field]),
+            }
+        }
+        result.extend_from_slice(&record[end..]);
+        result
+    }
+
+    /// Append `value` to `output` as a field, quoted if `quote` is set or it has to be.
+    fn push_field(&self, output: &mut Vec<u8>, value: &str, quote: bool) {
+        let quote = quote
+            || value
+                .bytes()
+                .any(|b| matches!(b, b'"' | b'\r' | b'\n') || b == self.delimiter);
+        if !quote {
+            output.extend_from_slice(value.as_bytes());
+            return;
+        }
+        output.push(b'"');
+        output.extend_from_slice(value.replace('"', "\"\"").as_bytes());
+        output.push(b'"');
+    }
+
+    /// The cleaned field, if it changed.
+    fn clean_field(&self, field: &[u8]) -> Option<String> {
+        let field = core::str::from_utf8(field).ok()?;
+        match self.cleaner.clear_url(field).ok()? {
+            Cow::Owned(result) if result != field => Some(result),
+            _ => None,
+        }
+    }
+}
+
+fn invalid_input(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+/// A reader that keeps the bytes read from it, so that records can be copied as they are.
+#[derive(Debug)]
+struct Tee<R> {
+    inner: R,
+    /// The bytes read that weren't copied yet
+    buffer: Vec<u8>,
+    /// The offset of `buffer` in the input
+    start: u64,
+}
+
+impl<R> Tee<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            buffer: Vec::new(),
+            start: 0,
+        }
+    }
+}
+
+impl<R: Read> Read for Tee<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.buffer.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+}
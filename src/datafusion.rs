@@ -0,0 +1,105 @@
+//! A `clean_url(text)` scalar function for [`datafusion`](https://docs.rs/datafusion).
+//!
+//! ```
+//! # use std::sync::Arc;
+//! # use clearurls::UrlCleaner;
+//! # use datafusion::prelude::SessionContext;
+//! # fn main() -> Result<(), clearurls::Error> {
+//! let ctx = SessionContext::new();
+//! clearurls::datafusion::register(&ctx, Arc::new(UrlCleaner::from_embedded_rules()?));
+//! // ctx.sql("SELECT clean_url(link) FROM messages").await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use alloc::sync::Arc;
+use core::hash::{Hash, Hasher};
+
+use ::datafusion::arrow::array::StringArray;
+use ::datafusion::arrow::compute::cast;
+use ::datafusion::arrow::datatypes::DataType;
+use ::datafusion::common::cast::as_string_array;
+use ::datafusion::common::Result;
+use ::datafusion::logical_expr::{
+    ColumnarValue, ScalarFunctionArgs, ScalarUDF, ScalarUDFImpl, Signature, Volatility,
+};
+use ::datafusion::prelude::SessionContext;
+use ::datafusion::scalar::ScalarValue;
+
+use crate::UrlCleaner;
+
+/// Register [`CleanUrlUdf`] with `ctx`, as `clean_url`.
+pub fn register(ctx: &SessionContext, cleaner: Arc<UrlCleaner>) {
+    ctx.register_udf(ScalarUDF::from(CleanUrlUdf::new(cleaner)));
+}
+
+/// The `clean_url(text)` scalar function, which cleans each URL with a [`UrlCleaner`].
+///
+/// It takes any string type and returns `Utf8`. URLs that can't be cleaned, and nulls,
+/// become null.
+#[derive(Debug)]
+pub struct CleanUrlUdf {
+    cleaner: Arc<UrlCleaner>,
+    signature: Signature,
+}
+
+impl CleanUrlUdf {
+    /// Construct the function, cleaning URLs with `cleaner`.
+    #[must_use]
+    pub fn new(cleaner: Arc<UrlCleaner>) -> Self {
+        Self {
+            cleaner,
+            signature: Signature::string(1, Volatility::Immutable),
+        }
+    }
+}
+
+// functions are equal if they use the same cleaner
+impl PartialEq for CleanUrlUdf {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.cleaner, &other.cleaner)
+    }
+}
+
+impl Eq for CleanUrlUdf {}
+
+impl Hash for CleanUrlUdf {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Arc::as_ptr(&self.cleaner).hash(state);
+    }
+}
+
+impl ScalarUDFImpl for CleanUrlUdf {
+    fn name(&self) -> &'static str {
+        "clean_url"
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType> {
+        Ok(DataType::Utf8)
+    }
+
+    fn invoke_with_args(&self, args: ScalarFunctionArgs) -> Result<ColumnarValue> {
+        let arrays = ColumnarValue::values_to_arrays(&args.args)?;
+        let urls = cast(&arrays[0], &DataType::Utf8)?;
+        let cleaned: StringArray = as_string_array(&urls)?
+            .iter()
+            .map(|url| url.and_then(|url| self.cleaner.clear_url(url).ok()))
+            .collect();
+        // a scalar argument, as in `clean_url('https://...')`, gives a scalar
+        if args
+            .args
+            .iter()
+            .all(|arg| matches!(arg, ColumnarValue::Scalar(_)))
+        {
+            Ok(ColumnarValue::Scalar(ScalarValue::try_from_array(
+                &cleaned, 0,
+            )?))
+        } else {
+            Ok(ColumnarValue::Array(Arc::new(cleaned)))
+        }
+    }
+}
@@ -1,34 +1,49 @@
 use alloc::borrow::Cow;
 use alloc::fmt;
+use alloc::string::String;
 use alloc::vec::Vec;
 use core::marker::PhantomData;
 
-use regex::{Regex, RegexBuilder, RegexSet, RegexSetBuilder};
-use serde::de::{Error as _, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use regex::{RegexBuilder, RegexSetBuilder};
+use serde::de::{Error as _, MapAccess, SeqAccess, Visitor};
 use serde::{Deserialize, Deserializer};
 
-/// Deserialize a [`Regex`]
+use crate::pattern::{Pattern, PatternSet};
+
+/// Compile a [`Pattern`] with the `case_insensitive` flag set.
+pub(crate) fn build_pattern<E: serde::de::Error>(s: &str) -> Result<Pattern, E> {
+    match RegexBuilder::new(s).case_insensitive(true).build() {
+        Ok(regex) => Ok(Pattern::Regex(regex)),
+        #[cfg(feature = "fancy-regex")]
+        Err(_) => fancy_regex::RegexBuilder::new(s)
+            .case_insensitive(true)
+            .build()
+            .map(Pattern::Fancy)
+            .map_err(E::custom),
+        #[cfg(not(feature = "fancy-regex"))]
+        Err(e) => Err(E::custom(e)),
+    }
+}
+
+/// Deserialize a [`Pattern`]
 /// The result will have the `case_insensitive` flag set.
-pub(crate) fn deserialize_regex<'de, D>(d: D) -> Result<Regex, D::Error>
+pub(crate) fn deserialize_pattern<'de, D>(d: D) -> Result<Pattern, D::Error>
 where
     D: Deserializer<'de>,
 {
     let s = <Cow<'_, str>>::deserialize(d)?;
-    RegexBuilder::new(&s)
-        .case_insensitive(true)
-        .build()
-        .map_err(D::Error::custom)
+    build_pattern(&s)
 }
 
-/// Deserialize a [`Vec<Regex>`].
-/// All regexes will have the `case_insensitive` flag set.
-pub(crate) fn deserialize_regex_vec<'de, D>(d: D) -> Result<Vec<Regex>, D::Error>
+/// Deserialize a [`Vec<Pattern>`].
+/// All patterns will have the `case_insensitive` flag set.
+pub(crate) fn deserialize_pattern_vec<'de, D>(d: D) -> Result<Vec<Pattern>, D::Error>
 where
     D: Deserializer<'de>,
 {
-    struct RegexVecVisitor;
-    impl<'a> Visitor<'a> for RegexVecVisitor {
-        type Value = Vec<Regex>;
+    struct PatternVecVisitor;
+    impl<'a> Visitor<'a> for PatternVecVisitor {
+        type Value = Vec<Pattern>;
 
         fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
             formatter.write_str("valid sequence")
@@ -41,41 +56,78 @@ where
             let cap = seq.size_hint().unwrap_or(0);
             let mut vec = Vec::with_capacity(cap);
             while let Some(el) = seq.next_element::<Cow<'_, str>>()? {
-                let regex = RegexBuilder::new(&el)
-                    .case_insensitive(true)
-                    .build()
-                    .map_err(A::Error::custom)?;
-                vec.push(regex);
+                vec.push(build_pattern(&el)?);
             }
             Ok(vec)
         }
     }
 
-    d.deserialize_seq(RegexVecVisitor)
+    d.deserialize_seq(PatternVecVisitor)
+}
+
+/// Deserialize the redirections of a provider, see [`deserialize_pattern_vec`].
+/// Patterns compiled with `fancy_regex` must have a capture group for the target.
+pub(crate) fn deserialize_redirections<'de, D>(d: D) -> Result<Vec<Pattern>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let patterns = deserialize_pattern_vec(d)?;
+    #[cfg(feature = "fancy-regex")]
+    for p in &patterns {
+        if let Pattern::Fancy(r) = p {
+            if r.captures_len() < 2 {
+                return Err(D::Error::custom(format_args!(
+                    "redirection regex {r} has no capture group"
+                )));
+            }
+        }
+    }
+    Ok(patterns)
 }
 
-/// Deserialize a [`RegexSet`].
-/// All regexes will have the `case_insensitive` flag set.
-pub(crate) fn deserialize_regex_set<'de, D>(d: D) -> Result<RegexSet, D::Error>
+/// Deserialize a [`PatternSet`].
+/// All patterns will have the `case_insensitive` flag set.
+pub(crate) fn deserialize_pattern_set<'de, D>(d: D) -> Result<PatternSet, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let regexes = <Vec<Cow<'_, str>>>::deserialize(d)?;
-    RegexSetBuilder::new(regexes)
+    let patterns = <Vec<Cow<'_, str>>>::deserialize(d)?;
+    #[cfg(feature = "fancy-regex")]
+    let (patterns, fancy) = {
+        let (regex, fancy): (Vec<_>, Vec<_>) = patterns
+            .into_iter()
+            .partition(|p| RegexBuilder::new(p).build().is_ok());
+        let fancy = fancy
+            .iter()
+            .map(|p| {
+                fancy_regex::RegexBuilder::new(p)
+                    .case_insensitive(true)
+                    .build()
+                    .map_err(D::Error::custom)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        (regex, fancy)
+    };
+    let set = RegexSetBuilder::new(patterns)
         .case_insensitive(true)
         .build()
-        .map_err(D::Error::custom)
+        .map_err(D::Error::custom)?;
+    Ok(PatternSet {
+        set,
+        #[cfg(feature = "fancy-regex")]
+        fancy,
+    })
 }
 
-/// Deserialize a [`Vec`] from a map by ignoring the keys.
-pub(crate) fn deserialize_map_as_vec<'de, D, T>(d: D) -> Result<Vec<T>, D::Error>
+/// Deserialize a [`Vec`] of the entries of a map with string keys, in their order.
+pub(crate) fn deserialize_map_as_vec<'de, D, T>(d: D) -> Result<Vec<(String, T)>, D::Error>
 where
     D: Deserializer<'de>,
     T: Deserialize<'de>,
 {
     struct MapAsVecVisitor<T>(PhantomData<T>);
     impl<'de, T: Deserialize<'de>> Visitor<'de> for MapAsVecVisitor<T> {
-        type Value = Vec<T>;
+        type Value = Vec<(String, T)>;
 
         fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
             formatter.write_str("valid map")
@@ -87,8 +139,8 @@ where
         {
             let cap = map.size_hint().unwrap_or(0);
             let mut vec = Vec::with_capacity(cap);
-            while let Some((_, v)) = map.next_entry::<IgnoredAny, T>()? {
-                vec.push(v);
+            while let Some(entry) = map.next_entry::<String, T>()? {
+                vec.push(entry);
             }
             Ok(vec)
         }
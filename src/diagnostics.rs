@@ -0,0 +1,87 @@
+//! Events about what cleaning a URL did, emitted with the `tracing` and `log` features,
+//! and counted with the `metrics` feature. Without them, these functions do nothing.
+#![cfg_attr(
+    not(any(feature = "tracing", feature = "log")),
+    allow(unused_variables)
+)]
+
+use crate::{Error, Redirection};
+
+/// Entered while a URL is cleaned, so that the events are attributed to it.
+pub(crate) struct Span {
+    #[cfg(feature = "tracing")]
+    _entered: tracing::span::EnteredSpan,
+    #[cfg(feature = "metrics")]
+    start: std::time::Instant,
+}
+
+#[cfg(feature = "metrics")]
+impl Drop for Span {
+    fn drop(&mut self) {
+        metrics::histogram!("clearurls_clean_duration_seconds").record(self.start.elapsed());
+    }
+}
+
+pub(crate) fn clean_span(url: &str) -> Span {
+    #[cfg(feature = "log")]
+    log::debug!("cleaning {url}");
+    Span {
+        #[cfg(feature = "tracing")]
+        _entered: tracing::debug_span!("clear_url", url).entered(),
+        #[cfg(feature = "metrics")]
+        start: std::time::Instant::now(),
+    }
+}
+
+pub(crate) fn provider_matched(provider: &str, url: &str) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(provider, url, "provider matched");
+    #[cfg(feature = "log")]
+    log::debug!("provider {provider} matched {url}");
+}
+
+pub(crate) fn parameter_removed(provider: &str, parameter: &str) {
+    #[cfg(feature = "tracing")]
+    tracing::trace!(provider, parameter, "parameter removed");
+    #[cfg(feature = "log")]
+    log::trace!("provider {provider} removed parameter {parameter}");
+    #[cfg(feature = "metrics")]
+    metrics::counter!("clearurls_parameters_removed_total").increment(1);
+}
+
+pub(crate) fn redirection_followed(redirection: &Redirection) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        provider = redirection.provider,
+        pattern = redirection.pattern,
+        decodes = redirection.decodes,
+        target = redirection.target,
+        "redirection followed",
+    );
+    #[cfg(feature = "log")]
+    log::debug!(
+        "provider {} redirected to {}",
+        redirection.provider,
+        redirection.target,
+    );
+    #[cfg(feature = "metrics")]
+    metrics::counter!("clearurls_redirects_followed_total").increment(1);
+}
+
+pub(crate) fn cleaned(result: Result<&str, &Error>) {
+    #[cfg(feature = "tracing")]
+    match result {
+        Ok(url) => tracing::debug!(url, "cleaned"),
+        Err(error) => tracing::debug!(%error, "cleaning failed"),
+    }
+    #[cfg(feature = "log")]
+    match result {
+        Ok(url) => log::debug!("cleaned to {url}"),
+        Err(error) => log::debug!("cleaning failed: {error}"),
+    }
+    #[cfg(feature = "metrics")]
+    match result {
+        Ok(_) => metrics::counter!("clearurls_urls_cleaned_total").increment(1),
+        Err(_) => metrics::counter!("clearurls_errors_total").increment(1),
+    }
+}
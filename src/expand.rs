@@ -0,0 +1,228 @@
+//! Expansion of links of URL shorteners like `bit.ly`, which can only be resolved by
+//! requesting them.
+//!
+//! This crate doesn't do any requests itself. Implement [`Fetch`] with the HTTP client of
+//! your choice, and pass it to an [`Expander`].
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+use core::future::{poll_fn, Future};
+use core::pin::pin;
+use core::task::Poll;
+use core::time::Duration;
+
+use url::Url;
+
+use crate::catalog::has_host;
+use crate::{Error, UrlCleaner};
+
+/// The hosts of URL shorteners that an [`Expander`] follows the redirects of by default, the
+/// ones of the [catalog](crate::catalog::SHORTENERS).
+pub const DEFAULT_SHORTENERS: &[&str] = crate::catalog::SHORTENERS;
+
+/// How many redirects an [`Expander`] follows at most by default.
+pub const DEFAULT_MAX_HOPS: usize = 5;
+
+/// How long an [`Expander`] waits for a response by default.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The method of a request of an [`Expander`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Method {
+    /// `HEAD`, which is enough for most shorteners
+    Head,
+    /// `GET`, for shorteners that don't redirect `HEAD` requests
+    Get,
+}
+
+/// A way to make HTTP requests, for an [`Expander`].
+pub trait Fetch {
+    /// The error of a failed request
+    type Error;
+
+    /// Request `url` with `method` without following redirects, and return the `Location`
+    /// header if the response is a redirect. The body of the response isn't needed.
+    ///
+    /// # Errors
+    /// If the request failed or didn't complete within `timeout`.
+    fn location(
+        &self,
+        url: &str,
+        method: Method,
+        timeout: Duration,
+    ) -> impl Future<Output = Result<Option<String>, Self::Error>> + Send;
+
+    /// Wait for `duration`, like `tokio::time::sleep`. Requests that take longer than the
+    /// [timeout][Expander::timeout] are given up on, even if [`Self::location`] doesn't
+    /// enforce it.
+    fn sleep(&self, duration: Duration) -> impl Future<Output = ()> + Send;
+}
+
+/// Follows the redirects of URL shortener links, and cleans the destination with a
+/// [`UrlCleaner`].
+///
+/// Only URLs whose host is one of the [shorteners][Self::shorteners], or a subdomain of one,
+/// are requested, so that arbitrary URLs can be passed without leaking them. Links are
+/// requested with `HEAD`, and with `GET` if that isn't redirected.
+#[derive(Debug)]
+pub struct Expander<'a, F> {
+    cleaner: &'a UrlCleaner,
+    fetch: F,
+    shorteners: Vec<String>,
+    max_hops: usize,
+    timeout: Duration,
+}
+
+impl<'a, F: Fetch> Expander<'a, F> {
+    /// Construct an [`Expander`] that makes requests with `fetch`.
+    pub fn new(cleaner: &'a UrlCleaner, fetch: F) -> Self {
+        Self {
+            cleaner,
+            fetch,
+            shorteners: DEFAULT_SHORTENERS
+                .iter()
+                .map(|s| String::from(*s))
+                .collect(),
+            max_hops: DEFAULT_MAX_HOPS,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Configure the hosts of URL shorteners whose redirects are followed.
+    ///
+    /// Hosts are compared case-insensitively, and also apply to their subdomains.
+    /// The default is [`DEFAULT_SHORTENERS`].
+    #[must_use]
+    pub fn shorteners<I, S>(mut self, hosts: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.shorteners = hosts
+            .into_iter()
+            .map(|s| s.as_ref().to_ascii_lowercase())
+            .collect();
+        self
+    }
+
+    /// Configure how many redirects are followed at most.
+    ///
+    /// The default is [`DEFAULT_MAX_HOPS`].
+    #[must_use]
+    pub fn max_hops(mut self, value: usize) -> Self {
+        self.max_hops = value;
+        self
+    }
+
+    /// Configure how long to wait for each response, which is passed to [`Fetch::location`].
+    /// Requests that take longer fail with [`ExpandError::Timeout`].
+    ///
+    /// The default is [`DEFAULT_TIMEOUT`].
+    #[must_use]
+    pub fn timeout(mut self, value: Duration) -> Self {
+        self.timeout = value;
+        self
+    }
+
+    /// Follow the redirects of `url` while it's a shortener link, and clean the result.
+    ///
+    /// # Errors
+    /// If a request failed or timed out, there were more redirects than
+    /// [allowed][Self::max_hops], a redirect led to a URL that isn't `http` or `https`, or
+    /// the result couldn't be cleaned. See [`ExpandError`].
+    pub async fn expand(&self, url: &str) -> Result<String, ExpandError<F::Error>> {
+        let mut url = String::from(url);
+        let mut hops = 0;
+        while self.is_shortener(&url) {
+            let location = match self.request(&url, Method::Head).await? {
+                Some(location) => location,
+                None => match self.request(&url, Method::Get).await? {
+                    Some(location) => location,
+                    None => break,
+                },
+            };
+            if hops == self.max_hops {
+                return Err(ExpandError::TooManyHops(self.max_hops));
+            }
+            hops += 1;
+            // the location may be relative
+            let target = Url::parse(&url)
+                .and_then(|base| base.join(&location))
+                .map_err(|e| ExpandError::Clean(e.into()))?;
+            if !matches!(target.scheme(), "http" | "https") {
+                return Err(ExpandError::UnsafeLocation(location));
+            }
+            url = target.into();
+        }
+        let cleaned = self.cleaner.clear_url(&url).map_err(ExpandError::Clean)?;
+        Ok(cleaned.into_owned())
+    }
+
+    /// Request `url`, and give up after the timeout.
+    async fn request(
+        &self,
+        url: &str,
+        method: Method,
+    ) -> Result<Option<String>, ExpandError<F::Error>> {
+        let mut request = pin!(self.fetch.location(url, method, self.timeout));
+        let mut timeout = pin!(self.fetch.sleep(self.timeout));
+        poll_fn(|cx| {
+            if let Poll::Ready(result) = request.as_mut().poll(cx) {
+                return Poll::Ready(result.map_err(ExpandError::Fetch));
+            }
+            timeout
+                .as_mut()
+                .poll(cx)
+                .map(|()| Err(ExpandError::Timeout(self.timeout)))
+        })
+        .await
+    }
+
+    fn is_shortener(&self, url: &str) -> bool {
+        has_host(url, &self.shorteners)
+    }
+}
+
+/// Errors that can happen while [expanding][Expander::expand] a URL.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ExpandError<E> {
+    /// A request failed
+    Fetch(E),
+    /// A request didn't complete within the [timeout][Expander::timeout]
+    Timeout(Duration),
+    /// The shortener redirected more often than the given maximum
+    TooManyHops(usize),
+    /// The shortener redirected to this location, which isn't an `http` or `https` URL,
+    /// like a `javascript:` URL
+    UnsafeLocation(String),
+    /// The expanded URL is invalid or couldn't be cleaned
+    Clean(Error),
+}
+
+impl<E: Display> Display for ExpandError<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ExpandError::Fetch(x) => write!(f, "error requesting url: {x}"),
+            ExpandError::Timeout(x) => write!(f, "no response within {x:?}"),
+            ExpandError::TooManyHops(x) => write!(f, "more than {x} redirects"),
+            ExpandError::UnsafeLocation(x) => write!(f, "redirect to {x} is unsafe"),
+            ExpandError::Clean(x) => write!(f, "error cleaning expanded url: {x}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: std::error::Error + 'static> std::error::Error for ExpandError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ExpandError::Fetch(e) => Some(e),
+            ExpandError::Timeout(_)
+            | ExpandError::TooManyHops(_)
+            | ExpandError::UnsafeLocation(_) => None,
+            ExpandError::Clean(e) => Some(e),
+        }
+    }
+}
@@ -0,0 +1,56 @@
+//! Cleaning the links in RSS and Atom feeds.
+//!
+//! [`clean_feed`] cleans the `link`s of channels, items and entries, the URLs of
+//! `enclosure`s, and the links in the HTML of `content:encoded`, `description`, and Atom's
+//! `content` and `summary`, with [`html::clean_document`](crate::html::clean_document).
+//! Everything else is kept byte for byte.
+//!
+//! ```
+//! # use clearurls::UrlCleaner;
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let cleaner = UrlCleaner::from_embedded_rules()?;
+//! let rss = "<rss><channel><item><link>https://example.com/?utm_source=rss</link></item></channel></rss>";
+//! assert_eq!(
+//!     clearurls::feed::clean_feed(&cleaner, rss)?,
+//!     "<rss><channel><item><link>https://example.com/</link></item></channel></rss>",
+//! );
+//! # Ok(())
+//! # }
+//! ```
+
+use alloc::borrow::Cow;
+
+use crate::html::clean_document;
+use crate::xml::{clean_url, local_name};
+use crate::UrlCleaner;
+
+/// Clean the links in an RSS or Atom feed.
+///
+/// URLs that can't be cleaned, like relative ones, are left unchanged.
+///
+/// # Errors
+/// If the feed isn't well-formed XML.
+pub fn clean_feed<'a>(
+    cleaner: &UrlCleaner,
+    xml: &'a str,
+) -> Result<Cow<'a, str>, quick_xml::Error> {
+    crate::xml::rewrite(
+        xml,
+        |element, attribute, value| match (local_name(element), local_name(attribute)) {
+            ("link", "href") | ("enclosure" | "source", "url") | ("content", "src") => {
+                clean_url(cleaner, value)
+            }
+            _ => None,
+        },
+        |element, text| match local_name(element) {
+            "link" | "comments" => clean_url(cleaner, text),
+            "encoded" | "description" | "content" | "summary" => {
+                match clean_document(cleaner, text) {
+                    Cow::Owned(result) => Some(result),
+                    Cow::Borrowed(_) => None,
+                }
+            }
+            _ => None,
+        },
+    )
+}
@@ -0,0 +1,172 @@
+//! A C API, declared in `include/clearurls.h`.
+//!
+//! Build it as a shared library with
+//! `cargo rustc --lib --crate-type cdylib --release --features ffi`, or as a static library
+//! with `--crate-type staticlib`. These crate types aren't declared in `Cargo.toml`, so that
+//! crates depending on this one don't build them too. The header is generated with `cbindgen`,
+//! see `cbindgen.toml`.
+
+use alloc::boxed::Box;
+use alloc::ffi::CString;
+use core::ffi::{c_char, CStr};
+use core::ptr;
+
+use crate::{Error, UrlCleaner};
+
+/// The result of a function of the C API.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ClearUrlsStatus {
+    /// Success
+    Ok = 0,
+    /// A pointer argument was null
+    NullPointer = 1,
+    /// A string argument is not valid UTF-8
+    InvalidUtf8 = 2,
+    /// The rules are invalid JSON, or not in the format of the browser extension
+    RuleSyntax = 3,
+    /// The URL can't be parsed, or has syntax violations that the parse mode rejects
+    UrlSyntax = 4,
+    /// A redirection can't be followed, like one that leads to itself
+    Redirection = 5,
+    /// The cleaned URL contains a NUL byte, so it can't be returned as a C string
+    InteriorNul = 6,
+    /// Any other error
+    Other = 255,
+}
+
+impl From<&Error> for ClearUrlsStatus {
+    fn from(e: &Error) -> Self {
+        match e {
+            Error::RuleSyntax(_) => Self::RuleSyntax,
+            Error::UrlSyntax(_) | Error::StrictSyntaxViolation(_) => Self::UrlSyntax,
+            Error::RedirectionHasNoCapturingGroup(_)
+            | Error::PercentDecodeUtf8Error(_)
+            | Error::RedirectionCycle(_)
+            | Error::RedirectionTooDeeplyEncoded(_)
+            | Error::UnsafeRedirectionTarget(_) => Self::Redirection,
+            #[cfg(feature = "std")]
+            Error::FileRead(_) => Self::Other,
+            #[cfg(feature = "audit")]
+            Error::AuditLog(_) => Self::Other,
+        }
+    }
+}
+
+/// Construct a cleaner with the rules embedded in the library.
+///
+/// # Returns
+/// the cleaner, which must be freed with `clearurls_free`, or null if the embedded rules
+/// can't be parsed
+#[unsafe(no_mangle)]
+pub extern "C" fn clearurls_new() -> *mut UrlCleaner {
+    match UrlCleaner::from_embedded_rules() {
+        Ok(cleaner) => Box::into_raw(Box::new(cleaner)),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Construct a cleaner with rules in the JSON format of the browser extension. On success,
+/// the cleaner is written to `out`, and must be freed with `clearurls_free`.
+///
+/// # Safety
+/// `rules` must be null or a NUL-terminated string, and `out` must be null or valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn clearurls_from_rules(
+    rules: *const c_char,
+    out: *mut *mut UrlCleaner,
+) -> ClearUrlsStatus {
+    if out.is_null() {
+        return ClearUrlsStatus::NullPointer;
+    }
+    // SAFETY: guaranteed by the caller
+    let rules = match unsafe { to_str(rules) } {
+        Ok(rules) => rules,
+        Err(status) => return status,
+    };
+    match UrlCleaner::from_rules_str(rules) {
+        Ok(cleaner) => {
+            // SAFETY: guaranteed by the caller
+            unsafe { out.write(Box::into_raw(Box::new(cleaner))) };
+            ClearUrlsStatus::Ok
+        }
+        Err(e) => ClearUrlsStatus::from(&e),
+    }
+}
+
+/// Clean a URL. On success, the cleaned URL is written to `out`, and must be freed with
+/// `clearurls_string_free`.
+///
+/// # Safety
+/// `cleaner` must be null or returned by `clearurls_new` or `clearurls_from_rules` and
+/// not freed yet, `url` must be null or a NUL-terminated string, and `out` must be null or
+/// valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn clearurls_clean(
+    cleaner: *const UrlCleaner,
+    url: *const c_char,
+    out: *mut *mut c_char,
+) -> ClearUrlsStatus {
+    if out.is_null() {
+        return ClearUrlsStatus::NullPointer;
+    }
+    // SAFETY: guaranteed by the caller
+    let Some(cleaner) = (unsafe { cleaner.as_ref() }) else {
+        return ClearUrlsStatus::NullPointer;
+    };
+    // SAFETY: guaranteed by the caller
+    let url = match unsafe { to_str(url) } {
+        Ok(url) => url,
+        Err(status) => return status,
+    };
+    let result = match cleaner.clear_url(url) {
+        Ok(result) => result,
+        Err(e) => return ClearUrlsStatus::from(&e),
+    };
+    match CString::new(result.into_owned()) {
+        Ok(result) => {
+            // SAFETY: guaranteed by the caller
+            unsafe { out.write(result.into_raw()) };
+            ClearUrlsStatus::Ok
+        }
+        Err(_) => ClearUrlsStatus::InteriorNul,
+    }
+}
+
+/// Free a URL returned by `clearurls_clean`. Null is ignored.
+///
+/// # Safety
+/// `s` must be null or returned by `clearurls_clean` and not freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn clearurls_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        // SAFETY: guaranteed by the caller
+        drop(unsafe { CString::from_raw(s) });
+    }
+}
+
+/// Free a cleaner. Null is ignored.
+///
+/// # Safety
+/// `cleaner` must be null or returned by `clearurls_new` or `clearurls_from_rules` and
+/// not freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn clearurls_free(cleaner: *mut UrlCleaner) {
+    if !cleaner.is_null() {
+        // SAFETY: guaranteed by the caller
+        drop(unsafe { Box::from_raw(cleaner) });
+    }
+}
+
+/// # Safety
+/// `s` must be null or a NUL-terminated string.
+unsafe fn to_str<'a>(s: *const c_char) -> Result<&'a str, ClearUrlsStatus> {
+    if s.is_null() {
+        return Err(ClearUrlsStatus::NullPointer);
+    }
+    // SAFETY: guaranteed by the caller
+    unsafe { CStr::from_ptr(s) }
+        .to_str()
+        .map_err(|_| ClearUrlsStatus::InvalidUtf8)
+}
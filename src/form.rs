@@ -0,0 +1,52 @@
+use alloc::borrow::Cow;
+use alloc::vec::Vec;
+use core::str::FromStr;
+
+use url::Url;
+
+use crate::rules::url_for_matching;
+use crate::{diagnostics, Error, UrlCleaner};
+
+impl UrlCleaner {
+    /// Clean an `application/x-www-form-urlencoded` request body, like a query, with the rules
+    /// of the providers that match the `url` the body is sent to.
+    ///
+    /// Retained parameters are kept as they were written. Redirections and raw rules only
+    /// apply to URLs, so they are ignored.
+    ///
+    /// ```
+    /// # use clearurls::UrlCleaner;
+    /// # fn main() -> Result<(), clearurls::Error> {
+    /// let cleaner = UrlCleaner::from_embedded_rules()?;
+    /// let body = cleaner.clean_form_body("https://example.com/login", "user=a&utm_source=x")?;
+    /// assert_eq!(body, "user=a");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    /// If `url` isn't a valid URL.
+    pub fn clean_form_body<'a>(&self, url: &str, body: &'a str) -> Result<Cow<'a, str>, Error> {
+        Url::from_str(url)?;
+        let mut body = Cow::Borrowed(body);
+        if self.config.is_passthrough(url) || self.config.is_exception(url) {
+            return Ok(body);
+        }
+        let matching_url = url_for_matching(url, &self.config);
+        for (name, p) in &self.rules.providers {
+            if !p.match_url(&matching_url) {
+                continue;
+            }
+            diagnostics::provider_matched(name, url);
+            let mut removed = Vec::new();
+            let cleaned = p.remove_fields_from_body(&body, &self.config, &mut removed);
+            if let Cow::Owned(cleaned) = cleaned {
+                body = Cow::Owned(cleaned);
+            }
+            for parameter in &removed {
+                diagnostics::parameter_removed(name, parameter);
+            }
+        }
+        Ok(body)
+    }
+}
@@ -0,0 +1,111 @@
+//! Cleaning HTTP Archives (HAR), which browsers' developer tools export, before they are shared.
+//!
+//! The URL and the `Referer` header of each request, and the `Location` header and the
+//! `redirectURL` of each response, are cleaned. Everything else is kept, in the same order.
+//!
+//! ```
+//! # use clearurls::UrlCleaner;
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let cleaner = UrlCleaner::from_embedded_rules()?;
+//! let har = br#"{"log": {"entries": [{"request": {"url": "https://example.com/?utm_source=x"}}]}}"#;
+//! let mut output = Vec::new();
+//! let changed = clearurls::har::rewrite(&cleaner, &har[..], &mut output)?;
+//! assert_eq!(changed, 1);
+//! # Ok(())
+//! # }
+//! ```
+
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use std::io::{Read, Write};
+
+use serde_json::{Map, Value};
+use url::Url;
+
+use crate::UrlCleaner;
+
+/// Clean the URLs in a parsed HAR, and return how many were changed.
+///
+/// If the URL of a request changes, its `queryString` is set to the parameters of the cleaned
+/// URL. URLs that can't be cleaned, like relative `Location`s, are left unchanged.
+pub fn clean_archive(cleaner: &UrlCleaner, har: &mut Value) -> usize {
+    let Some(entries) = har
+        .pointer_mut("/log/entries")
+        .and_then(Value::as_array_mut)
+    else {
+        return 0;
+    };
+    let mut changed = 0;
+    for entry in entries {
+        if let Some(request) = entry.get_mut("request").and_then(Value::as_object_mut) {
+            if let Some(url) = clean_field(cleaner, request, "url") {
+                changed += 1;
+                if let Ok(url) = Url::parse(&url) {
+                    let parameters = url
+                        .query_pairs()
+                        .map(|(name, value)| serde_json::json!({"name": name, "value": value}))
+                        .collect();
+                    request.insert("queryString".to_string(), Value::Array(parameters));
+                }
+            }
+            changed += clean_headers(cleaner, request, "referer");
+        }
+        if let Some(response) = entry.get_mut("response").and_then(Value::as_object_mut) {
+            changed += usize::from(clean_field(cleaner, response, "redirectURL").is_some());
+            changed += clean_headers(cleaner, response, "location");
+        }
+    }
+    changed
+}
+
+/// Read a HAR from `reader`, clean it with [`clean_archive`], write it to `writer`, and return
+/// how many URLs were changed.
+///
+/// # Errors
+/// If the HAR isn't valid JSON, or it can't be read or written.
+pub fn rewrite<R: Read, W: Write>(
+    cleaner: &UrlCleaner,
+    reader: R,
+    writer: W,
+) -> serde_json::Result<usize> {
+    let mut har = serde_json::from_reader(reader)?;
+    let changed = clean_archive(cleaner, &mut har);
+    serde_json::to_writer_pretty(writer, &har)?;
+    Ok(changed)
+}
+
+/// Clean the string in `object[name]`, and return it if it changed.
+fn clean_field(
+    cleaner: &UrlCleaner,
+    object: &mut Map<String, Value>,
+    name: &str,
+) -> Option<String> {
+    let Some(Value::String(url)) = object.get_mut(name) else {
+        return None;
+    };
+    match cleaner.clear_url(url) {
+        Ok(Cow::Owned(result)) if result != *url => {
+            url.clone_from(&result);
+            Some(result)
+        }
+        _ => None,
+    }
+}
+
+/// Clean the values of the headers named `name`, and return how many changed.
+fn clean_headers(cleaner: &UrlCleaner, object: &mut Map<String, Value>, name: &str) -> usize {
+    let Some(headers) = object.get_mut("headers").and_then(Value::as_array_mut) else {
+        return 0;
+    };
+    headers
+        .iter_mut()
+        .filter_map(Value::as_object_mut)
+        .filter(|header| {
+            header
+                .get("name")
+                .and_then(Value::as_str)
+                .is_some_and(|header| header.eq_ignore_ascii_case(name))
+        })
+        .map(|header| usize::from(clean_field(cleaner, header, "value").is_some()))
+        .sum()
+}
@@ -0,0 +1,114 @@
+//! Cleaning the URLs in the `Location` and `Refresh` headers of HTTP responses.
+//!
+//! ```
+//! # use clearurls::UrlCleaner;
+//! # use clearurls::headers::{clean_location, clean_refresh};
+//! # fn main() -> Result<(), clearurls::Error> {
+//! let cleaner = UrlCleaner::from_embedded_rules()?;
+//! let base = "https://example.com/login";
+//! assert_eq!(clean_location(&cleaner, base, "/home?utm_source=x"), "/home");
+//! assert_eq!(
+//!     clean_refresh(&cleaner, base, "5; url=https://example.com/?fbclid=x"),
+//!     "5; url=https://example.com/",
+//! );
+//! # Ok(())
+//! # }
+//! ```
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+use core::ops::Range;
+
+use url::Url;
+
+use crate::UrlCleaner;
+
+/// Clean the URL of a `Location` header value.
+///
+/// A relative location is resolved against `base`, the URL of the request. If it starts with
+/// `/`, the cleaned location is relative too, unless a redirection to another origin was
+/// followed. The value is returned unchanged if it can't be cleaned.
+#[must_use]
+pub fn clean_location<'a>(cleaner: &UrlCleaner, base: &str, value: &'a str) -> Cow<'a, str> {
+    match clean_url(cleaner, base, value.trim()) {
+        Some(result) => Cow::Owned(result),
+        None => Cow::Borrowed(value),
+    }
+}
+
+/// Clean the URL of a `Refresh` header value, like `5; url=https://example.com/`.
+///
+/// The delay, the separator and quotes around the URL are kept as they are written, and a
+/// relative URL is handled like by [`clean_location`].
+#[must_use]
+pub fn clean_refresh<'a>(cleaner: &UrlCleaner, base: &str, value: &'a str) -> Cow<'a, str> {
+    let url = refresh_url(value);
+    match clean_url(cleaner, base, &value[url.clone()]) {
+        Some(result) => Cow::Owned([&value[..url.start], &result, &value[url.end..]].concat()),
+        None => Cow::Borrowed(value),
+    }
+}
+
+/// The cleaned URL, if it changed.
+fn clean_url(cleaner: &UrlCleaner, base: &str, url: &str) -> Option<String> {
+    if url.is_empty() {
+        return None;
+    }
+    let absolute = match Url::parse(url) {
+        Ok(_) => Cow::Borrowed(url),
+        Err(url::ParseError::RelativeUrlWithoutBase) => {
+            Cow::Owned(Url::parse(base).ok()?.join(url).ok()?.into())
+        }
+        Err(_) => return None,
+    };
+    let result = cleaner.clear_url(&absolute).ok()?;
+    if result == absolute {
+        return None;
+    }
+    if url.starts_with('/') && !url.starts_with("//") {
+        let origin = Url::parse(&absolute).ok()?.origin().ascii_serialization();
+        if let Some(path) = result.strip_prefix(&*origin) {
+            if path.starts_with('/') {
+                return Some(path.into());
+            }
+        }
+    }
+    Some(result.into_owned())
+}
+
+/// The range of the URL in the content of a refresh, like `5; url='https://example.com/'`,
+/// without the quotes. It is empty if there is none.
+pub(crate) fn refresh_url(content: &str) -> Range<usize> {
+    let mut i = skip(content, 0, |c| c.is_ascii_whitespace());
+    i = skip(content, i, |c| c.is_ascii_digit() || c == '.');
+    i = skip(content, i, |c| c.is_ascii_whitespace());
+    if content[i..].starts_with([';', ',']) {
+        i = skip(content, i + 1, |c| c.is_ascii_whitespace());
+    }
+    if content
+        .get(i..i + 3)
+        .is_some_and(|url| url.eq_ignore_ascii_case("url"))
+    {
+        let equals = skip(content, i + 3, |c| c.is_ascii_whitespace());
+        if content[equals..].starts_with('=') {
+            i = skip(content, equals + 1, |c| c.is_ascii_whitespace());
+        }
+    }
+    match content[i..].chars().next() {
+        Some(quote @ ('"' | '\'')) => {
+            let start = i + 1;
+            let end = content[start..]
+                .find(quote)
+                .map_or(content.len(), |e| start + e);
+            start..end
+        }
+        _ => i..content.trim_end().len().max(i),
+    }
+}
+
+/// The index of the first character at or after `start` that doesn't match `f`.
+pub(crate) fn skip(input: &str, start: usize, f: impl Fn(char) -> bool) -> usize {
+    input[start..]
+        .find(|c: char| !f(c))
+        .map_or(input.len(), |i| start + i)
+}
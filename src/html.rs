@@ -0,0 +1,202 @@
+//! Cleaning the links in whole HTML documents.
+//!
+//! [`clean_document`] parses the document with [`lol_html`](https://docs.rs/lol_html) and cleans
+//! the URLs in `href`, `src`, `srcset` and `action` attributes and in
+//! `<meta http-equiv="refresh">` elements. Everything else is kept byte for byte.
+//! [`page_urls`] finds the canonical URL of a page.
+//!
+//! ```
+//! # use clearurls::UrlCleaner;
+//! # fn main() -> Result<(), clearurls::Error> {
+//! let cleaner = UrlCleaner::from_embedded_rules()?;
+//! let html = r#"<meta http-equiv="refresh" content="0; url=https://example.com/?utm_source=x">"#;
+//! let cleaned = clearurls::html::clean_document(&cleaner, html);
+//! assert_eq!(cleaned, r#"<meta http-equiv="refresh" content="0; url=https://example.com/">"#);
+//! # Ok(())
+//! # }
+//! ```
+
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+
+use ::lol_html::html_content::Element;
+use ::lol_html::{element, rewrite_str, RewriteStrSettings};
+
+use url::Url;
+
+use crate::headers::{refresh_url, skip};
+use crate::lol_html::{clean_value, element_content_handler};
+use crate::UrlCleaner;
+
+/// Clean the URLs in an HTML document.
+///
+/// `&amp;` in attribute values is decoded before cleaning, and encoded again after. URLs that
+/// can't be cleaned, like relative ones, are left unchanged, and so is the document if no URL
+/// changed.
+#[must_use]
+pub fn clean_document<'a>(cleaner: &UrlCleaner, html: &'a str) -> Cow<'a, str> {
+    let settings = RewriteStrSettings::new()
+        .append_element_content_handler(element_content_handler(cleaner))
+        .append_element_content_handler(element!("[srcset]", |el: &mut Element<'_, '_>| {
+            if let Some(srcset) = el.get_attribute("srcset") {
+                if let Some(result) = clean_srcset(cleaner, &srcset) {
+                    el.set_attribute("srcset", &result)?;
+                }
+            }
+            Ok(())
+        }))
+        .append_element_content_handler(element!(
+            "meta[http-equiv][content]",
+            |el: &mut Element<'_, '_>| {
+                let refresh = el
+                    .get_attribute("http-equiv")
+                    .is_some_and(|value| value.trim().eq_ignore_ascii_case("refresh"));
+                if let Some(content) = el.get_attribute("content").filter(|_| refresh) {
+                    if let Some(result) = clean_refresh(cleaner, &content) {
+                        el.set_attribute("content", &result)?;
+                    }
+                }
+                Ok(())
+            }
+        ));
+    match rewrite_str(html, settings) {
+        Ok(result) if result != html => Cow::Owned(result),
+        _ => Cow::Borrowed(html),
+    }
+}
+
+/// The cleaned URLs of a page, see [`page_urls`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PageUrls {
+    /// The URL the page was loaded from, cleaned, if it could be
+    pub page: Option<String>,
+    /// The `href` of the first `<link rel="canonical">`, cleaned
+    pub canonical: Option<String>,
+    /// The `content` of the first `<meta property="og:url">`, cleaned
+    pub og_url: Option<String>,
+}
+
+impl PageUrls {
+    /// The URL to save the page by: the canonical one if there is one, then the one of
+    /// `og:url`, then the one it was loaded from.
+    #[must_use]
+    pub fn preferred(&self) -> Option<&str> {
+        self.canonical
+            .as_deref()
+            .or(self.og_url.as_deref())
+            .or(self.page.as_deref())
+    }
+}
+
+/// Find the canonical URL and the `og:url` of an HTML document, or just its `<head>`, that was
+/// loaded from `page_url`, and clean them and `page_url`.
+///
+/// Relative URLs are resolved against `page_url`. URLs that can't be cleaned are left out.
+///
+/// ```
+/// # use clearurls::UrlCleaner;
+/// # fn main() -> Result<(), clearurls::Error> {
+/// let cleaner = UrlCleaner::from_embedded_rules()?;
+/// let html = r#"<head><link rel="canonical" href="/a?utm_source=x"></head>"#;
+/// let urls = clearurls::html::page_urls(&cleaner, "https://m.example.com/a?fbclid=x", html);
+/// assert_eq!(urls.page.as_deref(), Some("https://m.example.com/a"));
+/// assert_eq!(urls.preferred(), Some("https://m.example.com/a"));
+/// # Ok(())
+/// # }
+/// ```
+#[must_use]
+pub fn page_urls(cleaner: &UrlCleaner, page_url: &str, html: &str) -> PageUrls {
+    let mut canonical = None;
+    let mut og_url = None;
+    let settings = RewriteStrSettings::new()
+        .append_element_content_handler(element!(
+            "link[rel~=canonical][href]",
+            |el: &mut Element<'_, '_>| {
+                if canonical.is_none() {
+                    canonical = el.get_attribute("href");
+                }
+                Ok(())
+            }
+        ))
+        .append_element_content_handler(element!(
+            "meta[property=\"og:url\"][content]",
+            |el: &mut Element<'_, '_>| {
+                if og_url.is_none() {
+                    og_url = el.get_attribute("content");
+                }
+                Ok(())
+            }
+        ));
+    // the document is only read, and the handlers don't fail
+    drop(rewrite_str(html, settings));
+    let base = Url::parse(page_url).ok();
+    let clean = |url: Option<String>| {
+        let url = url?.trim().replace("&amp;", "&");
+        let url = match &base {
+            Some(base) => base.join(&url).ok()?.to_string(),
+            None => url,
+        };
+        cleaner.clear_url(&url).ok().map(Cow::into_owned)
+    };
+    PageUrls {
+        page: cleaner.clear_url(page_url).ok().map(Cow::into_owned),
+        canonical: clean(canonical),
+        og_url: clean(og_url),
+    }
+}
+
+/// Clean the URLs of the image candidates in a `srcset`, like `a.png 1x, b.png 2x`.
+fn clean_srcset(cleaner: &UrlCleaner, srcset: &str) -> Option<String> {
+    let mut result = String::with_capacity(srcset.len());
+    let mut changed = false;
+    let mut rest = srcset;
+    loop {
+        let start = skip(rest, 0, |c| c.is_ascii_whitespace() || c == ',');
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if rest.is_empty() {
+            break;
+        }
+        let end = rest
+            .find(|c: char| c.is_ascii_whitespace())
+            .unwrap_or(rest.len());
+        // commas at the end of the URL separate it from the next candidate
+        let url = rest[..end].trim_end_matches(',');
+        match clean_value(cleaner, url) {
+            Some(url) => {
+                result.push_str(&url);
+                changed = true;
+            }
+            None => result.push_str(url),
+        }
+        rest = &rest[url.len()..];
+        if url.len() == end {
+            let descriptors = descriptors_len(rest);
+            result.push_str(&rest[..descriptors]);
+            rest = &rest[descriptors..];
+        }
+    }
+    changed.then_some(result)
+}
+
+/// The length of the descriptors of an image candidate, up to the comma after them.
+fn descriptors_len(input: &str) -> usize {
+    let mut parens = false;
+    for (i, c) in input.char_indices() {
+        match c {
+            '(' => parens = true,
+            ')' => parens = false,
+            ',' if !parens => return i,
+            _ => {}
+        }
+    }
+    input.len()
+}
+
+/// Clean the URL in the `content` of a refresh, like `5; url='https://example.com/'`.
+fn clean_refresh(cleaner: &UrlCleaner, content: &str) -> Option<String> {
+    let url = refresh_url(content);
+    let result = clean_value(cleaner, &content[url.clone()])?;
+    Some([&content[..url.start], &result, &content[url.end..]].concat())
+}
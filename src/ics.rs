@@ -0,0 +1,161 @@
+//! Cleaning the URLs in iCalendar data (`.ics` files).
+//!
+//! [`clean_calendar`] cleans the values of `URL` and `ATTACH` properties, and the links in the
+//! text of `DESCRIPTION`s with [`UrlCleaner::clean_text`]. Folded lines are unfolded before
+//! cleaning, and changed lines are folded again. Everything else is kept byte for byte.
+//!
+//! ```
+//! # use clearurls::UrlCleaner;
+//! # fn main() -> Result<(), clearurls::Error> {
+//! let cleaner = UrlCleaner::from_embedded_rules()?;
+//! let ics = "BEGIN:VEVENT\r\nURL:https://example.com/?utm_source=invite\r\nEND:VEVENT\r\n";
+//! assert_eq!(
+//!     clearurls::ics::clean_calendar(&cleaner, ics),
+//!     "BEGIN:VEVENT\r\nURL:https://example.com/\r\nEND:VEVENT\r\n",
+//! );
+//! # Ok(())
+//! # }
+//! ```
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+use core::ops::Range;
+
+use crate::text::{to_str, Edits};
+use crate::UrlCleaner;
+
+/// The maximum length of a line in octets, without its line ending.
+const LINE_LENGTH: usize = 75;
+
+/// Clean the URLs in iCalendar data.
+///
+/// Escapes in a `DESCRIPTION` whose links changed are written again in their usual form,
+/// like `\n` for `\N`. URLs that can't be cleaned are left unchanged.
+#[must_use]
+pub fn clean_calendar<'a>(cleaner: &UrlCleaner, ics: &'a str) -> Cow<'a, str> {
+    let mut edits = Edits::new(ics.as_bytes());
+    let mut lines = lines(ics).peekable();
+    while let Some(first) = lines.next() {
+        let mut content = Cow::Borrowed(&ics[first.clone()]);
+        let mut end = first.end;
+        while let Some(next) = lines.next_if(|line| ics[line.clone()].starts_with([' ', '\t'])) {
+            content.to_mut().push_str(&ics[next.start + 1..next.end]);
+            end = next.end;
+        }
+        if let Some(result) = clean_property(cleaner, &content) {
+            let newline = if ics[first.end..].starts_with("\r\n") {
+                "\r\n"
+            } else {
+                "\n"
+            };
+            edits.replace(first.start..end, fold(&result, newline).as_bytes());
+        }
+    }
+    to_str(ics, edits.finish())
+}
+
+/// The ranges of the lines of `ics`, without their line endings.
+fn lines(ics: &str) -> impl Iterator<Item = Range<usize>> + '_ {
+    let mut start = 0;
+    core::iter::from_fn(move || {
+        if start >= ics.len() {
+            return None;
+        }
+        let end = ics[start..].find('\n').map_or(ics.len(), |i| start + i);
+        let line = start..end - usize::from(ics[start..end].ends_with('\r'));
+        start = end + 1;
+        Some(line)
+    })
+}
+
+/// The cleaned content line, like `URL:https://example.com/`, if it changed.
+fn clean_property(cleaner: &UrlCleaner, line: &str) -> Option<String> {
+    let colon = value_start(line)?;
+    let value = &line[colon + 1..];
+    let mut parameters = line[..colon].split(';');
+    let name = parameters.next()?;
+    let result = if name.eq_ignore_ascii_case("URL")
+        || name.eq_ignore_ascii_case("ATTACH")
+            && !parameters.any(|p| p.eq_ignore_ascii_case("VALUE=BINARY"))
+    {
+        match cleaner.clear_url(value) {
+            Ok(result) if result != value => result.into_owned(),
+            _ => return None,
+        }
+    } else if name.eq_ignore_ascii_case("DESCRIPTION") {
+        match cleaner.clean_text(&unescape(value)) {
+            Cow::Owned(result) => escape(&result),
+            Cow::Borrowed(_) => return None,
+        }
+    } else {
+        return None;
+    };
+    Some([&line[..=colon], &result].concat())
+}
+
+/// The index of the `:` that separates the name and parameters of a property from its value.
+/// Parameter values may contain `:` in double quotes.
+fn value_start(line: &str) -> Option<usize> {
+    let mut quoted = false;
+    line.char_indices().find_map(|(i, c)| match c {
+        '"' => {
+            quoted = !quoted;
+            None
+        }
+        ':' if !quoted => Some(i),
+        _ => None,
+    })
+}
+
+/// Decode the escapes of a text value, like `\,` and `\n`.
+fn unescape(value: &str) -> Cow<'_, str> {
+    if !value.contains('\\') {
+        return Cow::Borrowed(value);
+    }
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => result.push('\n'),
+            Some(escaped) => result.push(escaped),
+            None => result.push('\\'),
+        }
+    }
+    Cow::Owned(result)
+}
+
+/// Escape a text value, the reverse of [`unescape`].
+fn escape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => result.push_str("\\\\"),
+            ';' => result.push_str("\\;"),
+            ',' => result.push_str("\\,"),
+            '\n' => result.push_str("\\n"),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+/// Fold a content line into lines of at most [`LINE_LENGTH`] octets, without splitting
+/// characters.
+fn fold(line: &str, newline: &str) -> String {
+    let mut result = String::with_capacity(line.len() + line.len() / LINE_LENGTH * 3);
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > LINE_LENGTH {
+            result.push_str(newline);
+            result.push(' ');
+            length = 1;
+        }
+        result.push(c);
+        length += c.len_utf8();
+    }
+    result
+}
@@ -0,0 +1,113 @@
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::RangeInclusive;
+
+use crate::rules::{authority_range, host_range};
+
+/// Convert a URL to its display form.
+///
+/// The host is converted from punycode to Unicode, and percent-encoded UTF-8 sequences
+/// in the rest of the URL are decoded if they produce non-ASCII printable characters.
+pub(crate) fn to_iri(url: &str) -> Cow<'_, str> {
+    let (prefix, host, rest) = match authority_range(url) {
+        Some(authority) => {
+            let host = host_range(url, authority);
+            (&url[..host.start], &url[host.clone()], &url[host.end..])
+        }
+        None => ("", "", url),
+    };
+
+    let unicode_host = if host.starts_with("xn--") || host.contains(".xn--") {
+        match idna::domain_to_unicode(host) {
+            (unicode, Ok(())) => Cow::Owned(unicode),
+            (_, Err(_)) => Cow::Borrowed(host),
+        }
+    } else {
+        Cow::Borrowed(host)
+    };
+    let rest_decoded = decode_non_ascii(rest);
+
+    if matches!(unicode_host, Cow::Borrowed(_)) && matches!(rest_decoded, Cow::Borrowed(_)) {
+        return Cow::Borrowed(url);
+    }
+    Cow::Owned([prefix, &unicode_host, &rest_decoded].concat())
+}
+
+/// Decode runs of percent-encoded bytes `>= 0x80` that are valid UTF-8.
+fn decode_non_ascii(s: &str) -> Cow<'_, str> {
+    let bytes = s.as_bytes();
+    let mut out = String::new();
+    let mut copied_until = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let run_start = i;
+        let mut run = Vec::new();
+        while let Some(byte) = encoded_byte(bytes, i).filter(|b| *b >= 0x80) {
+            run.push(byte);
+            i += 3;
+        }
+        if run.is_empty() {
+            i += 1;
+            continue;
+        }
+        if let Ok(decoded) = core::str::from_utf8(&run) {
+            if decoded.chars().all(is_displayable) {
+                out.push_str(&s[copied_until..run_start]);
+                out.push_str(decoded);
+                copied_until = i;
+            }
+        }
+    }
+    if copied_until == 0 {
+        return Cow::Borrowed(s);
+    }
+    out.push_str(&s[copied_until..]);
+    Cow::Owned(out)
+}
+
+fn encoded_byte(bytes: &[u8], i: usize) -> Option<u8> {
+    if bytes.get(i) != Some(&b'%') {
+        return None;
+    }
+    let hex = |b: u8| char::from(b).to_digit(16);
+    let high = hex(*bytes.get(i + 1)?)?;
+    let low = hex(*bytes.get(i + 2)?)?;
+    u8::try_from(high * 16 + low).ok()
+}
+
+/// Characters that shouldn't be decoded because they are invisible or confusing,
+/// see [RFC 3987, section 4.1](https://www.rfc-editor.org/rfc/rfc3987#section-4.1).
+fn is_displayable(c: char) -> bool {
+    !c.is_control() && !c.is_whitespace() && !INVISIBLE.iter().any(|range| range.contains(&c))
+}
+
+/// The characters of the `Default_Ignorable_Code_Point` property and the other format
+/// characters (`Cf`), like zero width spaces, bidi controls and byte order marks.
+const INVISIBLE: &[RangeInclusive<char>] = &[
+    '\u{AD}'..='\u{AD}',
+    '\u{34F}'..='\u{34F}',
+    '\u{600}'..='\u{605}',
+    '\u{61C}'..='\u{61C}',
+    '\u{6DD}'..='\u{6DD}',
+    '\u{70F}'..='\u{70F}',
+    '\u{890}'..='\u{891}',
+    '\u{8E2}'..='\u{8E2}',
+    '\u{115F}'..='\u{1160}',
+    '\u{17B4}'..='\u{17B5}',
+    '\u{180B}'..='\u{180F}',
+    '\u{200B}'..='\u{200F}',
+    '\u{202A}'..='\u{202E}',
+    '\u{2060}'..='\u{206F}',
+    '\u{3164}'..='\u{3164}',
+    '\u{FE00}'..='\u{FE0F}',
+    '\u{FEFF}'..='\u{FEFF}',
+    '\u{FFA0}'..='\u{FFA0}',
+    '\u{FFF0}'..='\u{FFFB}',
+    '\u{110BD}'..='\u{110BD}',
+    '\u{110CD}'..='\u{110CD}',
+    '\u{13430}'..='\u{1343F}',
+    '\u{1BCA0}'..='\u{1BCA3}',
+    '\u{1D173}'..='\u{1D17A}',
+    '\u{E0000}'..='\u{E0FFF}',
+];
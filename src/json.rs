@@ -0,0 +1,147 @@
+//! Cleaning the URLs in the strings of JSON values, or only those at the [`JsonPaths`].
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use serde_json::Value;
+
+use crate::UrlCleaner;
+
+impl UrlCleaner {
+    /// Clean each string in `value` that is an `http` or `https` URL, and return how many
+    /// were changed.
+    ///
+    /// ```
+    /// # use clearurls::UrlCleaner;
+    /// # fn main() -> Result<(), clearurls::Error> {
+    /// let cleaner = UrlCleaner::from_embedded_rules()?;
+    /// let mut payload = serde_json::json!({"links": [{"url": "https://example.com/?utm_source=x"}]});
+    /// assert_eq!(cleaner.clean_json(&mut payload), 1);
+    /// assert_eq!(payload["links"][0]["url"], "https://example.com/");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn clean_json(&self, value: &mut Value) -> usize {
+        self.clean_json_paths(value, &JsonPaths::new())
+    }
+
+    /// Like [`Self::clean_json`], but only clean the strings selected by `paths`.
+    pub fn clean_json_paths(&self, value: &mut Value, paths: &JsonPaths) -> usize {
+        clean_value(self, value, paths, &mut Vec::new())
+    }
+}
+
+/// Which strings of a JSON document [`UrlCleaner::clean_json_paths`] cleans.
+///
+/// Paths are JSON pointers (RFC 6901), like `/entries/0/url`, in which a `*` segment matches
+/// any key or index. A string is cleaned if it is in one of the included paths, or there
+/// are none, and it isn't in an excluded path.
+///
+/// ```
+/// # use clearurls::{JsonPaths, UrlCleaner};
+/// # fn main() -> Result<(), clearurls::Error> {
+/// let cleaner = UrlCleaner::from_embedded_rules()?;
+/// let paths = JsonPaths::new().include("/links").exclude("/links/*/original");
+/// let mut payload = serde_json::json!({
+///     "links": [{"url": "https://example.com/?utm_source=x", "original": "https://example.com/?utm_source=x"}],
+///     "signature": "https://example.com/?utm_source=x",
+/// });
+/// assert_eq!(cleaner.clean_json_paths(&mut payload, &paths), 1);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JsonPaths {
+    include: Vec<Vec<String>>,
+    exclude: Vec<Vec<String>>,
+}
+
+impl JsonPaths {
+    /// All strings.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clean the strings in `pointer`.
+    #[must_use]
+    pub fn include(mut self, pointer: &str) -> Self {
+        self.include.push(segments(pointer));
+        self
+    }
+
+    /// Don't clean the strings in `pointer`.
+    #[must_use]
+    pub fn exclude(mut self, pointer: &str) -> Self {
+        self.exclude.push(segments(pointer));
+        self
+    }
+}
+
+fn segments(pointer: &str) -> Vec<String> {
+    pointer
+        .split('/')
+        .skip(1)
+        .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+        .collect()
+}
+
+/// Whether `path` is in `pattern`, or `pattern` itself.
+fn is_in(path: &[String], pattern: &[String]) -> bool {
+    pattern.len() <= path.len()
+        && pattern
+            .iter()
+            .zip(path)
+            .all(|(pattern, segment)| pattern == "*" || pattern == segment)
+}
+
+fn clean_value(
+    cleaner: &UrlCleaner,
+    value: &mut Value,
+    paths: &JsonPaths,
+    path: &mut Vec<String>,
+) -> usize {
+    if paths.exclude.iter().any(|pattern| is_in(path, pattern)) {
+        return 0;
+    }
+    match value {
+        Value::String(string) => {
+            let included = paths.include.is_empty()
+                || paths.include.iter().any(|pattern| is_in(path, pattern));
+            let is_http = ["http://", "https://"].iter().any(|scheme| {
+                string
+                    .get(..scheme.len())
+                    .is_some_and(|start| start.eq_ignore_ascii_case(scheme))
+            });
+            if !included || !is_http {
+                return 0;
+            }
+            match cleaner.clear_url(string) {
+                Ok(result) if result != *string => {
+                    *string = result.into_owned();
+                    1
+                }
+                _ => 0,
+            }
+        }
+        Value::Array(values) => {
+            let mut changed = 0;
+            for (i, value) in values.iter_mut().enumerate() {
+                path.push(i.to_string());
+                changed += clean_value(cleaner, value, paths, path);
+                path.pop();
+            }
+            changed
+        }
+        Value::Object(object) => {
+            let mut changed = 0;
+            for (key, value) in object.iter_mut() {
+                path.push(key.clone());
+                changed += clean_value(cleaner, value, paths, path);
+                path.pop();
+            }
+            changed
+        }
+        _ => 0,
+    }
+}
@@ -45,25 +45,345 @@ extern crate alloc;
 extern crate std;
 
 use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::cell::Cell;
 use core::fmt::{Display, Formatter};
 use core::str::Utf8Error;
 use std::fs::File;
 use regex::Regex;
-use url::ParseError;
+use url::{ParseError, SyntaxViolation, Url};
 
-use rules::Rules;
+use pattern::Pattern;
+use rules::{fragment_start, is_nested_url, scheme, url_for_matching, Rules};
 
+#[cfg(feature = "access-log")]
+pub mod access_log;
+mod amp;
+#[cfg(feature = "arbitrary")]
+pub mod arbitrary;
+#[cfg(feature = "audit")]
+pub mod audit;
+#[cfg(feature = "axum")]
+pub mod axum;
+#[cfg(feature = "bookmarks")]
+pub mod bookmarks;
+pub mod catalog;
+mod category;
+#[cfg(feature = "std")]
+pub mod clean_url;
+mod cleaned_url;
+#[cfg(feature = "component")]
+mod component;
+#[cfg(feature = "csv")]
+pub mod csv;
+#[cfg(feature = "datafusion")]
+pub mod datafusion;
 mod deserialize_utils;
+mod diagnostics;
+#[cfg(feature = "expand-shorteners")]
+pub mod expand;
+#[cfg(feature = "feed")]
+pub mod feed;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod form;
+#[cfg(feature = "har")]
+pub mod har;
+pub mod headers;
+#[cfg(feature = "html")]
+pub mod html;
+#[cfg(feature = "ics")]
+pub mod ics;
+#[cfg(feature = "iri")]
+mod iri;
+mod json;
+#[cfg(feature = "lol-html")]
+pub mod lol_html;
+#[cfg(feature = "mail")]
+pub mod mail;
+#[cfg(feature = "markdown")]
+pub mod markdown;
+#[cfg(feature = "node")]
+pub mod node;
+mod normalize;
+mod pattern;
+#[cfg(feature = "pdf")]
+pub mod pdf;
+pub mod pixel;
+#[cfg(feature = "polars")]
+mod polars;
+#[cfg(feature = "proptest")]
+pub mod proptest;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "reqwest-middleware")]
+pub mod reqwest;
+#[cfg(feature = "email-rewriters")]
+mod rewriters;
 mod rules;
+mod sanitize;
+#[cfg(feature = "sitemap")]
+pub mod sitemap;
+mod text;
+#[cfg(feature = "tower")]
+pub mod tower;
+mod transform;
+#[cfg(feature = "uniffi")]
+pub mod uniffi;
+#[cfg(feature = "warc")]
+pub mod warc;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(any(feature = "feed", feature = "sitemap"))]
+mod xml;
+#[cfg(feature = "uniffi")]
+::uniffi::setup_scaffolding!();
+
+pub use category::Category;
+pub use cleaned_url::CleanedUrl;
+pub use json::JsonPaths;
+pub use transform::{Then, UrlTransformer};
 
-/// A [`UrlCleaner`] can remove tracking parameters from URLs.
+/// A `UrlCleaner` can remove tracking parameters from URLs.
 ///
 /// This struct is relatively expensive to construct because it needs to parse the rules from JSON.
 /// It's recommended to create one per application and reuse it.
 #[derive(Debug)]
 pub struct UrlCleaner {
     rules: Rules,
-    strip_referral_marketing: bool,
+    config: Config,
+}
+
+/// The options of a [`UrlCleaner`] that are needed while cleaning.
+#[derive(Debug)]
+#[allow(clippy::struct_excessive_bools)]
+pub(crate) struct Config {
+    pub(crate) strip_referral_marketing: bool,
+    #[cfg(feature = "iri")]
+    pub(crate) iri_output: bool,
+    pub(crate) fragment_policy: FragmentPolicy,
+    pub(crate) remove_empty_query_and_fragment: bool,
+    pub(crate) decoded_raw_rules: bool,
+    pub(crate) reevaluate_after_raw_rules: bool,
+    /// lowercase
+    pub(crate) passthrough_schemes: Vec<String>,
+    /// lowercase
+    pub(crate) https_like_schemes: Vec<String>,
+    pub(crate) parse_mode: ParseMode,
+    pub(crate) non_utf8_redirect_policy: NonUtf8RedirectPolicy,
+    pub(crate) normalize: bool,
+    pub(crate) trailing_slash: TrailingSlash,
+    pub(crate) hash_in_query: HashInQuery,
+    pub(crate) exceptions: Vec<Pattern>,
+    pub(crate) exception_prefixes: Vec<String>,
+    pub(crate) sanitize_input: bool,
+    pub(crate) max_redirects: usize,
+    pub(crate) default_redirect_scheme: DefaultRedirectScheme,
+    pub(crate) max_redirect_decodes: usize,
+    pub(crate) unsafe_redirect_policy: UnsafeRedirectPolicy,
+    pub(crate) unwrap_amp: bool,
+    #[cfg(feature = "audit")]
+    pub(crate) audit_log: Option<audit::AuditLog>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            strip_referral_marketing: false,
+            #[cfg(feature = "iri")]
+            iri_output: false,
+            fragment_policy: FragmentPolicy::default(),
+            remove_empty_query_and_fragment: false,
+            decoded_raw_rules: false,
+            reevaluate_after_raw_rules: false,
+            passthrough_schemes: DEFAULT_PASSTHROUGH_SCHEMES
+                .iter()
+                .map(|s| (*s).to_string())
+                .collect(),
+            https_like_schemes: Vec::new(),
+            parse_mode: ParseMode::default(),
+            non_utf8_redirect_policy: NonUtf8RedirectPolicy::default(),
+            normalize: false,
+            trailing_slash: TrailingSlash::default(),
+            hash_in_query: HashInQuery::default(),
+            exceptions: Vec::new(),
+            exception_prefixes: Vec::new(),
+            sanitize_input: false,
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            default_redirect_scheme: DefaultRedirectScheme::default(),
+            max_redirect_decodes: DEFAULT_MAX_REDIRECT_DECODES,
+            unsafe_redirect_policy: UnsafeRedirectPolicy::default(),
+            unwrap_amp: false,
+            #[cfg(feature = "audit")]
+            audit_log: None,
+        }
+    }
+}
+
+impl Config {
+    pub(crate) fn is_https_like(&self, scheme: &str) -> bool {
+        self.https_like_schemes
+            .iter()
+            .any(|s| s.eq_ignore_ascii_case(scheme))
+    }
+
+    /// Whether one of the [exceptions][UrlCleaner::exceptions] applies to `url`.
+    fn is_exception(&self, url: &str) -> bool {
+        if self.exceptions.is_empty() && self.exception_prefixes.is_empty() {
+            return false;
+        }
+        let normalized = normalize_input(url, self.parse_mode).unwrap_or(Cow::Borrowed(url));
+        let url = url_for_matching(&normalized, self);
+        self.exceptions.iter().any(|r| r.is_match(&url))
+            || self.exception_prefixes.iter().any(|prefix| {
+                url.get(..prefix.len())
+                    .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+            })
+    }
+
+    fn is_passthrough(&self, url: &str) -> bool {
+        scheme(url).is_some_and(|scheme| {
+            self.passthrough_schemes
+                .iter()
+                .any(|s| s.eq_ignore_ascii_case(scheme))
+        })
+    }
+}
+
+/// The schemes of URLs that are returned unchanged by default,
+/// see [`UrlCleaner::passthrough_schemes`].
+pub const DEFAULT_PASSTHROUGH_SCHEMES: &[&str] = &[
+    "data",
+    "mailto",
+    "tel",
+    "sms",
+    "javascript",
+    "about",
+    "blob",
+];
+
+/// How many redirections are followed in a row by default,
+/// see [`UrlCleaner::max_redirects`].
+pub const DEFAULT_MAX_REDIRECTS: usize = 5;
+
+/// How often the target of a redirection is percent-decoded at most by default,
+/// see [`UrlCleaner::max_redirect_decodes`].
+pub const DEFAULT_MAX_REDIRECT_DECODES: usize = 8;
+
+/// How forgiving a [`UrlCleaner`] is when parsing its input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum ParseMode {
+    /// Accept everything a browser accepts, as specified by the
+    /// [WHATWG URL standard](https://url.spec.whatwg.org/). Input with backslashes,
+    /// tabs or newlines, missing slashes and similar mistakes is normalized before cleaning,
+    /// but returned as it is if nothing was removed from it and no redirection was followed.
+    /// Input that can't be parsed at all is returned unchanged if no provider applies to it.
+    /// This is the default.
+    #[default]
+    Whatwg,
+    /// Reject input with any of these mistakes, or that can't be parsed, with an [`Error`].
+    /// Only URLs in their normalized ASCII form with no syntax violations are accepted,
+    /// except that userinfo like `user:password@` is allowed. The same applies to the targets
+    /// of redirections.
+    Strict,
+}
+
+/// What a [`UrlCleaner`] does when the decoded target of a redirection is not valid UTF-8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum NonUtf8RedirectPolicy {
+    /// Return [`Error::PercentDecodeUtf8Error`]. This is the default.
+    #[default]
+    Error,
+    /// Replace the invalid bytes with `U+FFFD REPLACEMENT CHARACTER`.
+    Lossy,
+    /// Don't follow the redirection, and clean the URL containing it instead.
+    Ignore,
+}
+
+/// What a [`UrlCleaner`] does when the target of a redirection is unsafe to return.
+///
+/// Targets are unsafe if they have a scheme other than `http`, `https` or one of the
+/// [https-like schemes][UrlCleaner::https_like_schemes], like `javascript:` or `data:`,
+/// or if they have userinfo, which can make a URL like `https://trusted.example@evil.example`
+/// look like it leads to another host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum UnsafeRedirectPolicy {
+    /// Return [`Error::UnsafeRedirectionTarget`]. This is the default.
+    #[default]
+    Error,
+    /// Don't follow the redirection, and clean the URL containing it instead.
+    Ignore,
+    /// Follow the redirection anyway.
+    Allow,
+}
+
+/// The scheme a [`UrlCleaner`] assumes for the target of a redirection without one,
+/// like `example.com/a`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum DefaultRedirectScheme {
+    /// Assume `http`, like the [ClearURLs browser extension](https://github.com/ClearURLs/Addon).
+    /// This is the default.
+    #[default]
+    Http,
+    /// Assume `https`.
+    Https,
+    /// Use the scheme of the URL containing the redirection.
+    Inherit,
+}
+
+/// What [normalization][UrlCleaner::normalize] does with a slash at the end of the path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum TrailingSlash {
+    /// Leave the path as it is. This is the default.
+    #[default]
+    Keep,
+    /// Add a slash to the end of the path if there is none, like `/a/b` to `/a/b/`.
+    Add,
+    /// Remove a slash from the end of the path, like `/a/b/` to `/a/b`.
+    /// The path `/` of URLs like `https://example.com/` is kept.
+    Remove,
+}
+
+/// How a [`UrlCleaner`] treats the fragment (the part after `#`) of a URL.
+///
+/// A fragment that is a URL itself, like in `https://example.com/viewer#https://example.org/?x`,
+/// is cleaned as a URL with the providers that apply to it, unless the policy is
+/// [`FragmentPolicy::Never`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum FragmentPolicy {
+    /// Never remove anything from the fragment.
+    Never,
+    /// Only remove parameters if every `&`-separated part of the fragment is a `key=value` pair.
+    /// Plain anchors like `#section-2&more` are kept as they are.
+    Pairs,
+    /// Treat the fragment like a query string. This is the default.
+    #[default]
+    Always,
+}
+
+/// How a [`UrlCleaner`] treats a `#` that was likely meant to be part of a query value,
+/// like in `https://example.com/?q=C#&lang=en`.
+///
+/// Such a `#` is detected if the text after it contains a `&`, and no `=` before that, like
+/// `#&lang=en`. Other fragments, like `#section` or `#a=1&b=2`, are always fragments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum HashInQuery {
+    /// The first `#` starts the fragment, as specified by the
+    /// [WHATWG URL standard](https://url.spec.whatwg.org/). This is the default.
+    #[default]
+    Fragment,
+    /// Treat the `#` as part of the query value, and keep it as it is.
+    Keep,
+    /// Treat the `#` as part of the query value, and percent-encode it as `%23`.
+    Escape,
 }
 
 impl UrlCleaner {
@@ -81,28 +401,32 @@ impl UrlCleaner {
     #[cfg(feature = "std")]
     pub fn from_rules_file<R: std::io::Read>(reader: R) -> Result<Self, Error> {
         let buf = std::io::BufReader::new(reader);
-        Ok(Self {
-            rules: serde_json::from_reader(buf)?,
-            strip_referral_marketing: false,
-        })
+        Ok(Self::from_rules(serde_json::from_reader(buf)?))
     }
 
     /// # Errors
     /// See [`Error`]
     pub fn from_rules_str(rules: &str) -> Result<Self, Error> {
-        Ok(Self {
-            rules: serde_json::from_str(rules)?,
-            strip_referral_marketing: false,
-        })
+        Ok(Self::from_rules(serde_json::from_str(rules)?))
+    }
+
+    fn from_rules(rules: Rules) -> Self {
+        Self {
+            rules,
+            config: Config::default(),
+        }
     }
 
     /// Construct using the JSON embedded in this library.
     /// This may be outdated, but should provide a good baseline.
     ///
+    /// The cargo features `strip-embedded-referral-marketing` and `strip-embedded-redirections`
+    /// remove the respective parts from the embedded rules to save binary size and memory.
+    ///
     /// # Errors
     /// See [`Error`]
     pub fn from_embedded_rules() -> Result<Self, Error> {
-        Self::from_rules_str(include_str!("../data.minify.json"))
+        Self::from_rules_str(include_str!(concat!(env!("OUT_DIR"), "/data.minify.json")))
     }
 
     /// Configure whether you want to strip referral codes and similar parameters.
@@ -111,35 +435,691 @@ impl UrlCleaner {
     /// The default is `false`, meaning these are kept.
     #[must_use]
     pub fn strip_referral_marketing(mut self, value: bool) -> Self {
-        self.strip_referral_marketing = value;
+        self.config.strip_referral_marketing = value;
+        self
+    }
+
+    /// Configure whether cleaned URLs are returned in their display form (an IRI).
+    ///
+    /// Punycode hostnames are converted back to Unicode and percent-encoded non-ASCII text
+    /// is decoded, which is easier for humans to read. Percent-encoded ASCII, as well as
+    /// encoded whitespace and control characters, is left untouched.
+    /// The default is `false`, meaning URLs are returned as percent-encoded ASCII.
+    #[cfg(feature = "iri")]
+    #[must_use]
+    pub fn iri_output(mut self, value: bool) -> Self {
+        self.config.iri_output = value;
+        self
+    }
+
+    /// Configure how parameters in the fragment (the part after `#`) are treated.
+    ///
+    /// The default is [`FragmentPolicy::Always`].
+    #[must_use]
+    pub fn fragment_policy(mut self, value: FragmentPolicy) -> Self {
+        self.config.fragment_policy = value;
+        self
+    }
+
+    /// Configure whether an empty query (`https://example.com/?`) or fragment
+    /// (`https://example.com/#`) is removed from URLs that a provider applies to.
+    ///
+    /// A query or fragment that becomes empty because all of its parameters were removed
+    /// is always dropped. The default is `false`, meaning URLs keep an empty `?` or `#`.
+    #[must_use]
+    pub fn remove_empty_query_and_fragment(mut self, value: bool) -> Self {
+        self.config.remove_empty_query_and_fragment = value;
+        self
+    }
+
+    /// Configure whether `rawRules` are additionally applied to a percent-decoded view of
+    /// the URL, to catch trackers hidden behind encoding like `%3Futm_source%3D`.
+    ///
+    /// Only percent-encoded ASCII is decoded, and the matches are removed from the
+    /// original URL. The default is `false`.
+    #[must_use]
+    pub fn decoded_raw_rules(mut self, value: bool) -> Self {
+        self.config.decoded_raw_rules = value;
+        self
+    }
+
+    /// Configure whether providers and redirections are evaluated again after `rawRules`
+    /// rewrote the URL.
+    ///
+    /// A `rawRule` can change the URL enough that a redirection of the same provider, or a
+    /// provider that didn't match before, now applies. With this enabled, the redirections of
+    /// the provider are checked against the rewritten URL, and all providers are applied
+    /// again, up to a few times. The default is `false`.
+    #[must_use]
+    pub fn reevaluate_after_raw_rules(mut self, value: bool) -> Self {
+        self.config.reevaluate_after_raw_rules = value;
+        self
+    }
+
+    /// Configure the schemes of URLs that are returned unchanged, without applying any rules.
+    ///
+    /// Schemes are compared case-insensitively and given without the colon, like `"mailto"`.
+    /// The default is [`DEFAULT_PASSTHROUGH_SCHEMES`].
+    #[must_use]
+    pub fn passthrough_schemes<I, S>(mut self, schemes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.config.passthrough_schemes = schemes
+            .into_iter()
+            .map(|s| s.as_ref().to_ascii_lowercase())
+            .collect();
+        self
+    }
+
+    /// Configure schemes whose URLs are matched by providers as if they were `https` URLs.
+    ///
+    /// Most provider patterns start with `^https?://`, so they don't apply to URLs like
+    /// `wss://example.com/?...` or app deep links like `myapp://open?...`. With a scheme in
+    /// this list, providers see such URLs as `https://...`, while the scheme of the returned
+    /// URL is unchanged. Providers that match any URL, like the global rules, always apply.
+    /// Schemes are compared case-insensitively and given without the colon.
+    /// The default is an empty list.
+    #[must_use]
+    pub fn https_like_schemes<I, S>(mut self, schemes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.config.https_like_schemes = schemes
+            .into_iter()
+            .map(|s| s.as_ref().to_ascii_lowercase())
+            .collect();
+        self
+    }
+
+    /// Add exceptions, URLs matching one of these regexes are returned unchanged.
+    ///
+    /// This applies on top of the exceptions of the providers in the rules, for URLs that
+    /// match a provider but must not be changed. The regexes are compiled like the ones of
+    /// the rules, case-insensitively, and matched against the same form of the URL as
+    /// provider patterns, without userinfo or port. The targets of redirections are checked
+    /// too, and returned without cleaning them further if they match.
+    ///
+    /// # Errors
+    /// If one of the regexes is invalid, see [`Error::RuleSyntax`].
+    pub fn exceptions<I, S>(mut self, exceptions: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        for exception in exceptions {
+            let pattern =
+                deserialize_utils::build_pattern::<serde_json::Error>(exception.as_ref())?;
+            self.config.exceptions.push(pattern);
+        }
+        Ok(self)
+    }
+
+    /// Add exceptions, URLs starting with one of these prefixes, like
+    /// `"https://pay.example.com/"`, are returned unchanged.
+    ///
+    /// Prefixes are compared case-insensitively with the same form of the URL as
+    /// [`Self::exceptions`], and apply to the targets of redirections too.
+    #[must_use]
+    pub fn exception_prefixes<I, S>(mut self, prefixes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config
+            .exception_prefixes
+            .extend(prefixes.into_iter().map(Into::into));
+        self
+    }
+
+    /// Configure whether AMP cache and viewer URLs are unwrapped to the publisher's URL, which
+    /// is cleaned then.
+    ///
+    /// These are URLs of `*.cdn.ampproject.org` and `*.bing-amp.com`, like
+    /// `https://example-com.cdn.ampproject.org/c/s/example.com/a`, and Google AMP viewer URLs
+    /// like `https://www.google.com/amp/s/example.com/a`. Unwrapping them counts as a
+    /// redirection of the provider `AMP`. The default is `false`.
+    #[must_use]
+    pub fn unwrap_amp(mut self, value: bool) -> Self {
+        self.config.unwrap_amp = value;
+        self
+    }
+
+    /// Configure whether the input is cleaned up like a URL pasted from text first.
+    ///
+    /// This removes surrounding whitespace, zero-width characters like `U+200B`, and
+    /// punctuation at the end that likely belongs to the surrounding sentence, like the `).`
+    /// in `(see https://example.com/a).`. A `)` is kept if it closes a `(` in the URL.
+    /// The default is `false`.
+    #[must_use]
+    pub fn sanitize_input(mut self, value: bool) -> Self {
+        self.config.sanitize_input = value;
+        self
+    }
+
+    /// Configure how many redirections are followed in a row at most.
+    ///
+    /// The target of a redirection is cleaned with all providers again, and may be wrapped in
+    /// another redirection, like a Google link to a Facebook link to the destination. Once
+    /// `value` redirections were followed, further ones are not, and their URL is cleaned
+    /// instead. With `0`, redirections are not followed at all, which keeps wrapper URLs
+    /// like those of link gateways while still removing tracking parameters from them.
+    /// The default is [`DEFAULT_MAX_REDIRECTS`].
+    #[must_use]
+    pub fn max_redirects(mut self, value: usize) -> Self {
+        self.config.max_redirects = value;
+        self
+    }
+
+    /// Configure how often the target of a redirection is percent-decoded at most.
+    ///
+    /// Targets are decoded once, and again while their start is still percent-encoded, like
+    /// `https%3A%2F%2F…`, since some are encoded multiple times. URLs nested in a target stay
+    /// encoded, they are decoded when their redirection is followed. A target that is still
+    /// encoded after `value` decodes results in [`Error::RedirectionTooDeeplyEncoded`].
+    /// The default is [`DEFAULT_MAX_REDIRECT_DECODES`].
+    #[must_use]
+    pub fn max_redirect_decodes(mut self, value: usize) -> Self {
+        self.config.max_redirect_decodes = value;
+        self
+    }
+
+    /// Configure the scheme of redirection targets without one, like `example.com/a`.
+    ///
+    /// The default is [`DefaultRedirectScheme::Http`].
+    #[must_use]
+    pub fn default_redirect_scheme(mut self, value: DefaultRedirectScheme) -> Self {
+        self.config.default_redirect_scheme = value;
+        self
+    }
+
+    /// Configure what happens when the target of a redirection is unsafe, like a
+    /// `javascript:` URL. See [`UnsafeRedirectPolicy`] for which targets are unsafe.
+    ///
+    /// The default is [`UnsafeRedirectPolicy::Error`].
+    #[must_use]
+    pub fn unsafe_redirect_policy(mut self, value: UnsafeRedirectPolicy) -> Self {
+        self.config.unsafe_redirect_policy = value;
+        self
+    }
+
+    /// Configure how forgiving parsing of the input is.
+    ///
+    /// The default is [`ParseMode::Whatwg`].
+    #[must_use]
+    pub fn parse_mode(mut self, value: ParseMode) -> Self {
+        self.config.parse_mode = value;
         self
     }
 
-    /// Clean a URL. This may involve
+    /// Configure what happens when the target of a redirection is not valid UTF-8
+    /// after percent-decoding it.
+    ///
+    /// The default is [`NonUtf8RedirectPolicy::Error`].
+    #[must_use]
+    pub fn non_utf8_redirect_policy(mut self, value: NonUtf8RedirectPolicy) -> Self {
+        self.config.non_utf8_redirect_policy = value;
+        self
+    }
+
+    /// Configure whether cleaned URLs are normalized.
+    ///
+    /// This lowercases the scheme and host, removes default ports like `:443` for `https`,
+    /// resolves dot segments like `/a/../b`, and applies the [`TrailingSlash`] policy.
+    /// Normalized URLs are suitable for deduplication. The default is `false`.
+    #[must_use]
+    pub fn normalize(mut self, value: bool) -> Self {
+        self.config.normalize = value;
+        self
+    }
+
+    /// Configure what [normalization][Self::normalize] does with a slash at the end of the
+    /// path. This has no effect if normalization is disabled.
+    ///
+    /// The default is [`TrailingSlash::Keep`].
+    #[must_use]
+    pub fn trailing_slash(mut self, value: TrailingSlash) -> Self {
+        self.config.trailing_slash = value;
+        self
+    }
+
+    /// Configure how a `#` inside a query value is treated.
+    ///
+    /// The default is [`HashInQuery::Fragment`].
+    #[must_use]
+    pub fn hash_in_query(mut self, value: HashInQuery) -> Self {
+        self.config.hash_in_query = value;
+        self
+    }
+
+    /// Clean a URL. URLs with one of the [passthrough schemes][Self::passthrough_schemes],
+    /// or that one of the [exceptions][Self::exceptions] applies to, are returned unchanged.
+    /// Otherwise, this may involve
+    /// - 0. [sanitizing][Self::sanitize_input] the input, if configured, normalizing it
+    ///      according to the [`ParseMode`], and converting an internationalized hostname
+    ///      to punycode
     /// - 1. removing tracking parameters
     ///      and/or,
-    /// - 2. detecting redirections with the target url in a query parameters
+    /// - 2. detecting redirections with the target url in a query parameters, and with the
+    ///      `email-rewriters` feature, in links rewritten by email security gateways like
+    ///      Microsoft Outlook Safe Links and Proofpoint URL Defense, and in AMP URLs if they
+    ///      are [unwrapped][Self::unwrap_amp]
+    /// - 3. [normalizing][Self::normalize] the URL, if configured
     ///
     /// # Returns
     /// a cleaned URL
     ///
     /// # Errors
     /// If an error occurred. See the [`Error`] enum for possible reasons.
+    ///
+    /// # Panics
+    /// Never. Any input, including input that isn't a URL at all, results in either a cleaned
+    /// URL or an [`Error`]. This is tested with the strategies of the `proptest` module and
+    /// fuzzed with the targets in `fuzz/`, so a panic is a bug.
     pub fn clear_url<'a>(&self, url: &'a str) -> Result<Cow<'a, str>, Error> {
-        if url.starts_with("data:") {
+        self.clear_url_with_report(url).map(|report| report.url)
+    }
+
+    /// Like [`Self::clear_url`], but also report the providers that matched, the parameters
+    /// they removed and the redirections that were followed, to find out why a URL was
+    /// cleaned like it was.
+    ///
+    /// With the `metrics` feature, these metrics are recorded for each call of this method
+    /// and [`Self::clear_url`]:
+    /// - `clearurls_urls_cleaned_total`: a counter of the URLs that were cleaned
+    /// - `clearurls_errors_total`: a counter of the URLs that couldn't be cleaned
+    /// - `clearurls_parameters_removed_total`: a counter of the removed parameters
+    /// - `clearurls_redirects_followed_total`: a counter of the followed redirections
+    /// - `clearurls_clean_duration_seconds`: a histogram of how long cleaning took
+    ///
+    /// # Errors
+    /// If an error occurred. See the [`Error`] enum for possible reasons.
+    pub fn clear_url_with_report<'a>(&self, url: &'a str) -> Result<Report<'a>, Error> {
+        let _span = diagnostics::clean_span(url);
+        let mut trace = Trace::default();
+        let result = if self.config.sanitize_input {
+            match sanitize::sanitize(url) {
+                Cow::Borrowed(url) => self.clean(url, &mut trace),
+                Cow::Owned(url) => self
+                    .clean(&url, &mut trace)
+                    .map(|cleaned| Cow::Owned(cleaned.into_owned())),
+            }
+        } else {
+            self.clean(url, &mut trace)
+        };
+        diagnostics::cleaned(result.as_deref());
+        let report = Report {
+            url: result?,
+            providers: trace.providers,
+            removed_parameters: trace.removed_parameters,
+            redirections: trace.redirections,
+        };
+        #[cfg(feature = "audit")]
+        if let Some(log) = &self.config.audit_log {
+            if report.url != url {
+                log.record(url, &report)?;
+            }
+        }
+        Ok(report)
+    }
+
+    fn clean<'a>(&self, url: &'a str, trace: &mut Trace) -> Result<Cow<'a, str>, Error> {
+        if self.config.is_passthrough(url) || self.config.is_exception(url) {
             return Ok(Cow::Borrowed(url));
         }
-        let mut result = Cow::Borrowed(url);
-        for p in &self.rules.providers {
-            if p.match_url(&result) {
-                let cleaned = p.remove_fields_from_url(&result, self.strip_referral_marketing)?;
-                // TODO get rid of the allocation
-                result = Cow::Owned(cleaned.into_owned());
+        let (mut result, exception) = self.apply_providers(url, 0, trace)?;
+        // a redirection target that is an exception isn't normalized either
+        if exception {
+            return Ok(result);
+        }
+
+        if self.config.normalize {
+            let normalized = normalize::normalize(&result, self.config.trailing_slash)?;
+            if let Cow::Owned(normalized) = normalized {
+                result = Cow::Owned(normalized);
+            }
+        }
+        #[cfg(feature = "iri")]
+        if self.config.iri_output {
+            if let Cow::Owned(iri) = iri::to_iri(&result) {
+                result = Cow::Owned(iri);
             }
         }
 
         Ok(result)
     }
+
+    /// Normalize the input and apply the providers to it, and to a URL in its fragment.
+    /// `depth` is the number of URLs `url` is nested in. What was done is added to `trace`.
+    ///
+    /// # Returns
+    /// the result, and whether it is a redirection target that is an exception, which the
+    /// providers weren't applied to
+    fn apply_providers<'a>(
+        &self,
+        url: &'a str,
+        depth: usize,
+        trace: &mut Trace,
+    ) -> Result<(Cow<'a, str>, bool), Error> {
+        let mut result = normalize_input(url, self.config.parse_mode)?;
+        // to return the input as it is if the providers didn't change it
+        let normalized = matches!(result, Cow::Owned(_)).then(|| result.to_string());
+        let mut redirects = 0;
+        // the input and the redirection targets, to detect cycles
+        let mut visited = Vec::new();
+        if self.config.max_redirects > 0 {
+            visited.push(result.to_string());
+        }
+        let mut passes = 0;
+        while passes < MAX_PASSES {
+            passes += 1;
+            let follow_redirections = redirects < self.config.max_redirects;
+            let (raw_rules_applied, redirection) =
+                self.apply_providers_once(&mut result, follow_redirections, trace)?;
+            if let Some(mut redirection) = redirection {
+                // start over with the target
+                let target =
+                    normalize_input(&redirection.target, self.config.parse_mode)?.into_owned();
+                if visited.contains(&target) || target == *result {
+                    return Err(Error::RedirectionCycle(target));
+                }
+                visited.push(target.clone());
+                redirection.target.clone_from(&target);
+                diagnostics::redirection_followed(&redirection);
+                trace.redirections.push(redirection);
+                // an exception is returned as it is, like when it is the input
+                if self.config.is_exception(&target) {
+                    return Ok((Cow::Owned(target), true));
+                }
+                result = Cow::Owned(target);
+                redirects += 1;
+                passes = 0;
+            } else if !(raw_rules_applied && self.config.reevaluate_after_raw_rules) {
+                break;
+            }
+        }
+        if redirects == 0 && normalized.as_deref() == Some(&*result) {
+            result = Cow::Borrowed(url);
+        }
+
+        if depth < MAX_NESTING && self.config.fragment_policy != FragmentPolicy::Never {
+            let nested = fragment_start(&result, &self.config).and_then(|i| {
+                let (before, fragment) = (&result[..i], &result[i + 1..]);
+                if !is_nested_url(fragment)
+                    || self.config.is_passthrough(fragment)
+                    || self.config.is_exception(fragment)
+                {
+                    return None;
+                }
+                // a nested URL that can't be cleaned is kept as it is
+                let mut nested_trace = Trace::default();
+                match self.apply_providers(fragment, depth + 1, &mut nested_trace) {
+                    Ok((Cow::Owned(cleaned), _)) => {
+                        trace.append(nested_trace);
+                        Some([before, "#", &cleaned].concat())
+                    }
+                    Ok((Cow::Borrowed(_), _)) | Err(_) => None,
+                }
+            });
+            if let Some(nested) = nested {
+                result = Cow::Owned(nested);
+            }
+        }
+        Ok((result, false))
+    }
+
+    /// Apply each provider to `url` once.
+    ///
+    /// # Returns
+    /// whether `rawRules` changed the URL, and the redirection if one was found.
+    /// In that case, the remaining providers weren't applied.
+    fn apply_providers_once(
+        &self,
+        url: &mut Cow<'_, str>,
+        follow_redirections: bool,
+        trace: &mut Trace,
+    ) -> Result<(bool, Option<Redirection>), Error> {
+        if follow_redirections && self.config.unwrap_amp {
+            if let Some(target) = amp::unwrap(url) {
+                if rules::check_target(&target, &target, &self.config)? {
+                    let redirection = Redirection {
+                        provider: "AMP".into(),
+                        pattern: None,
+                        decodes: 0,
+                        target,
+                    };
+                    return Ok((false, Some(redirection)));
+                }
+            }
+        }
+        #[cfg(feature = "email-rewriters")]
+        if follow_redirections {
+            if let Some((gateway, target)) = rewriters::unwrap(url) {
+                if rules::check_target(&target, &target, &self.config)? {
+                    let redirection = Redirection {
+                        provider: gateway.into(),
+                        pattern: None,
+                        decodes: 1,
+                        target,
+                    };
+                    return Ok((false, Some(redirection)));
+                }
+            }
+        }
+        let mut raw_rules_applied = false;
+        for (name, p) in &self.rules.providers {
+            let matching_url = url_for_matching(url, &self.config);
+            if p.match_url(&matching_url) {
+                diagnostics::provider_matched(name, url);
+                trace.add_provider(name);
+                let cleaned = p.remove_fields_from_url(
+                    url,
+                    &matching_url,
+                    &self.config,
+                    follow_redirections,
+                )?;
+                if let Some(origin) = cleaned.redirected {
+                    let redirection = Redirection {
+                        provider: name.clone(),
+                        pattern: Some(origin.pattern),
+                        decodes: origin.decodes,
+                        target: cleaned.url.into_owned(),
+                    };
+                    return Ok((raw_rules_applied, Some(redirection)));
+                }
+                raw_rules_applied |= cleaned.raw_rules_applied;
+                for parameter in &cleaned.removed {
+                    diagnostics::parameter_removed(name, parameter);
+                }
+                trace.removed_parameters.extend(cleaned.removed);
+                if let Cow::Owned(cleaned) = cleaned.url {
+                    *url = Cow::Owned(cleaned);
+                }
+            }
+        }
+        Ok((raw_rules_applied, None))
+    }
+
+    /// Check that cleaning the result of [`Self::clear_url`] again doesn't change it.
+    ///
+    /// Cleaning is meant to be idempotent, so that cleaned URLs can be compared or used as
+    /// keys of a cache. This is useful in tests and debug assertions, to catch URLs for which
+    /// it is not, for example because of rules that contradict each other.
+    ///
+    /// # Errors
+    /// If an error occurred while cleaning `url` or its result. See [`Error`].
+    pub fn verify_idempotent(&self, url: &str) -> Result<bool, Error> {
+        let once = self.clear_url(url)?;
+        let twice = self.clear_url(&once)?;
+        Ok(once == twice)
+    }
+
+    /// Clean each URL of `vectors` and compare the result with the expected one.
+    ///
+    /// The vectors are pairs of a URL and the result the
+    /// [ClearURLs browser extension](https://github.com/ClearURLs/Addon) gives for it,
+    /// like the test cases published with the extension. This can be used to find out where
+    /// this crate behaves differently with the same rules. Configure the [`UrlCleaner`] like the
+    /// extension was configured, for example with [`Self::strip_referral_marketing`].
+    ///
+    /// # Returns
+    /// the vectors this [`UrlCleaner`] gave another result or an error for, in their order
+    pub fn divergences<'v, I>(&self, vectors: I) -> Vec<Divergence<'v>>
+    where
+        I: IntoIterator<Item = (&'v str, &'v str)>,
+    {
+        vectors
+            .into_iter()
+            .filter_map(|(url, expected)| {
+                let actual = self.clear_url(url).map(Cow::into_owned);
+                match &actual {
+                    Ok(actual) if actual == expected => None,
+                    _ => Some(Divergence {
+                        url,
+                        expected,
+                        actual,
+                    }),
+                }
+            })
+            .collect()
+    }
+}
+
+/// The result of [`UrlCleaner::clear_url_with_report`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct Report<'a> {
+    /// The cleaned URL, like [`UrlCleaner::clear_url`] returns it
+    pub url: Cow<'a, str>,
+    /// The names of the providers that matched, in the order they first did
+    pub providers: Vec<String>,
+    /// The query and fragment parameters that were removed, as they were written,
+    /// like `utm_source=abc`
+    pub removed_parameters: Vec<String>,
+    /// The redirections that were followed, in their order
+    pub redirections: Vec<Redirection>,
+}
+
+/// What cleaning a URL did, for a [`Report`].
+#[derive(Default)]
+struct Trace {
+    providers: Vec<String>,
+    removed_parameters: Vec<String>,
+    redirections: Vec<Redirection>,
+}
+
+impl Trace {
+    fn add_provider(&mut self, name: &str) {
+        if !self.providers.iter().any(|p| p == name) {
+            self.providers.push(name.into());
+        }
+    }
+
+    fn append(&mut self, other: Trace) {
+        for name in &other.providers {
+            self.add_provider(name);
+        }
+        self.removed_parameters.extend(other.removed_parameters);
+        self.redirections.extend(other.redirections);
+    }
+}
+
+/// A redirection that was followed while cleaning a URL, see
+/// [`UrlCleaner::clear_url_with_report`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Redirection {
+    /// The name of the provider in the rules, `AMP` for [unwrapped][UrlCleaner::unwrap_amp]
+    /// AMP URLs, or with the `email-rewriters` feature, the name of the email security
+    /// gateway, like `Safe Links`
+    pub provider: String,
+    /// The redirection pattern that matched, or `None` for AMP URLs and email security gateways
+    pub pattern: Option<String>,
+    /// How often the target was decoded, including base64 decoding
+    pub decodes: usize,
+    /// The target, before it was cleaned
+    pub target: String,
+}
+
+/// Counts of what cleaning URLs did, like the badge of the browser extension shows them.
+///
+/// Record each [`Report`] of a page or a session, and [`reset`][Stats::reset] the counts
+/// when it's over.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Stats {
+    /// How many URLs were cleaned
+    pub urls: usize,
+    /// How many query and fragment parameters were removed
+    pub removed_parameters: usize,
+    /// How many redirections were followed
+    pub redirections: usize,
+}
+
+impl Stats {
+    /// Add what cleaning a URL did to the counts.
+    pub fn record(&mut self, report: &Report<'_>) {
+        self.urls += 1;
+        self.removed_parameters += report.removed_parameters.len();
+        self.redirections += report.redirections.len();
+    }
+
+    /// The removed parameters and followed redirections together, the number on the badge.
+    #[must_use]
+    pub fn total(&self) -> usize {
+        self.removed_parameters + self.redirections
+    }
+
+    /// Set all counts to zero.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// A URL that a [`UrlCleaner`] cleaned differently than expected,
+/// see [`UrlCleaner::divergences`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct Divergence<'a> {
+    /// The URL that was cleaned
+    pub url: &'a str,
+    /// The expected result
+    pub expected: &'a str,
+    /// The result of this crate
+    pub actual: Result<String, Error>,
+}
+
+/// How often the providers are applied to a URL at most,
+/// see [`UrlCleaner::reevaluate_after_raw_rules`].
+const MAX_PASSES: usize = 4;
+
+/// How deep URLs in the fragments of URLs are cleaned at most, see [`FragmentPolicy`].
+const MAX_NESTING: usize = 4;
+
+/// Bring the input into the form provider patterns are written against, see [`ParseMode`].
+///
+/// Internationalized hostnames are converted to punycode, and input with syntax violations
+/// is replaced by its normalized serialization. Input without either is returned unchanged.
+/// In the [`ParseMode::Whatwg`] mode, input that can't be parsed is returned unchanged too.
+fn normalize_input(url: &str, mode: ParseMode) -> Result<Cow<'_, str>, Error> {
+    let violation = Cell::new(None);
+    let record_violation = |v| {
+        if v != SyntaxViolation::EmbeddedCredentials {
+            violation.set(violation.get().or(Some(v)));
+        }
+    };
+    let parsed = Url::options()
+        .syntax_violation_callback(Some(&record_violation))
+        .parse(url);
+    match (parsed, violation.get(), mode) {
+        (Err(e), _, ParseMode::Strict) => Err(Error::UrlSyntax(e)),
+        (Ok(_), Some(v), ParseMode::Strict) => Err(Error::StrictSyntaxViolation(v)),
+        (Ok(parsed), Some(_), ParseMode::Whatwg) => Ok(Cow::Owned(parsed.into())),
+        (Ok(parsed), None, _) if !url.is_ascii() => Ok(Cow::Owned(parsed.into())),
+        _ => Ok(Cow::Borrowed(url)),
+    }
 }
 
 /// Various errors that can happen while cleaning a URL
@@ -157,6 +1137,18 @@ pub enum Error {
     RedirectionHasNoCapturingGroup(Regex),
     /// Bytes that are invalid UTF-8
     PercentDecodeUtf8Error(Utf8Error),
+    /// The URL is not accepted by [`ParseMode::Strict`].
+    StrictSyntaxViolation(SyntaxViolation),
+    /// Following redirections led to this URL again, like a URL that redirects to itself.
+    RedirectionCycle(String),
+    /// The target of a redirection is still percent-encoded after decoding it this often,
+    /// see [`UrlCleaner::max_redirect_decodes`].
+    RedirectionTooDeeplyEncoded(usize),
+    /// The target of a redirection is unsafe to return, see [`UnsafeRedirectPolicy`].
+    UnsafeRedirectionTarget(String),
+    /// The record of a changed URL couldn't be written, see [`UrlCleaner::audit_log`].
+    #[cfg(feature = "audit")]
+    AuditLog(std::io::Error),
 }
 
 impl Display for Error {
@@ -172,6 +1164,14 @@ impl Display for Error {
             Error::PercentDecodeUtf8Error(x) => {
                 write!(f, "percent decoding resulted in non-UTF-8 bytes: {x}")
             }
+            Error::StrictSyntaxViolation(x) => write!(f, "url is not strictly valid: {x}"),
+            Error::RedirectionCycle(x) => write!(f, "redirections lead to {x} again"),
+            Error::RedirectionTooDeeplyEncoded(x) => {
+                write!(f, "redirection target is encoded more than {x} times")
+            }
+            Error::UnsafeRedirectionTarget(x) => write!(f, "redirection target {x} is unsafe"),
+            #[cfg(feature = "audit")]
+            Error::AuditLog(x) => write!(f, "error writing audit log: {x}"),
         }
     }
 }
@@ -208,8 +1208,14 @@ impl std::error::Error for Error {
             Error::FileRead(e) => Some(e),
             Error::RuleSyntax(e) => Some(e),
             Error::UrlSyntax(e) => Some(e),
-            Error::RedirectionHasNoCapturingGroup(_) => None,
-            Error::PercentDecodeUtf8Error(e) => Some(e)
+            Error::RedirectionHasNoCapturingGroup(_)
+            | Error::StrictSyntaxViolation(_)
+            | Error::RedirectionCycle(_)
+            | Error::RedirectionTooDeeplyEncoded(_)
+            | Error::UnsafeRedirectionTarget(_) => None,
+            Error::PercentDecodeUtf8Error(e) => Some(e),
+            #[cfg(feature = "audit")]
+            Error::AuditLog(e) => Some(e),
         }
     }
 }
@@ -0,0 +1,98 @@
+//! Element content handlers for [`lol_html`](https://docs.rs/lol_html) that clean the URLs
+//! in `href`, `src` and `action` attributes while HTML is streamed through a rewriter, without
+//! buffering the whole document.
+//!
+//! ```
+//! # use clearurls::UrlCleaner;
+//! # use lol_html::{HtmlRewriter, Settings};
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let cleaner = UrlCleaner::from_embedded_rules()?;
+//! let mut output = Vec::new();
+//! let mut rewriter = HtmlRewriter::new(
+//!     Settings::new()
+//!         .append_element_content_handler(clearurls::lol_html::element_content_handler(&cleaner)),
+//!     |chunk: &[u8]| output.extend_from_slice(chunk),
+//! );
+//! rewriter.write(b"<a href=\"https://example.com/?utm_source=x\">")?;
+//! rewriter.write(b"link</a>")?;
+//! rewriter.end()?;
+//! assert_eq!(output, b"<a href=\"https://example.com/\">link</a>");
+//! # Ok(())
+//! # }
+//! ```
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+
+use ::lol_html::html_content::Element;
+use ::lol_html::send;
+use ::lol_html::{ElementContentHandlers, HandlerResult, HandlerTypes, Selector};
+
+use crate::UrlCleaner;
+
+/// The attributes whose URLs are cleaned.
+pub const ATTRIBUTES: [&str; 3] = ["href", "src", "action"];
+
+/// A `(selector, handlers)` pair for [`Settings::append_element_content_handler`] that cleans
+/// the URLs in the [`ATTRIBUTES`] of all elements with `cleaner`.
+///
+/// `&amp;` in attribute values is decoded before cleaning, and encoded again after.
+/// Attributes that can't be cleaned, like relative URLs, are left unchanged.
+///
+/// [`Settings::append_element_content_handler`]: ::lol_html::Settings::append_element_content_handler
+#[must_use]
+pub fn element_content_handler(
+    cleaner: &UrlCleaner,
+) -> (Cow<'static, Selector>, ElementContentHandlers<'_>) {
+    (
+        selector(),
+        ElementContentHandlers::default().element(|el: &mut Element<'_, '_>| clean(cleaner, el)),
+    )
+}
+
+/// Like [`element_content_handler`], for [`Send`]able rewriters.
+#[must_use]
+pub fn element_content_handler_send(
+    cleaner: &UrlCleaner,
+) -> (Cow<'static, Selector>, send::ElementContentHandlers<'_>) {
+    (
+        selector(),
+        send::ElementContentHandlers::default()
+            .element(|el: &mut send::Element<'_, '_>| clean(cleaner, el)),
+    )
+}
+
+fn selector() -> Cow<'static, Selector> {
+    Cow::Owned("[href], [src], [action]".parse().unwrap())
+}
+
+fn clean<H: HandlerTypes>(cleaner: &UrlCleaner, el: &mut Element<'_, '_, H>) -> HandlerResult {
+    for name in ATTRIBUTES {
+        let Some(value) = el.get_attribute(name) else {
+            continue;
+        };
+        if let Some(result) = clean_value(cleaner, &value) {
+            el.set_attribute(name, &result)?;
+        }
+    }
+    Ok(())
+}
+
+/// Clean the URL in a raw attribute value, or `None` if it didn't change.
+pub(crate) fn clean_value(cleaner: &UrlCleaner, value: &str) -> Option<String> {
+    let escaped = value.contains("&amp;");
+    let url = if escaped {
+        Cow::Owned(value.replace("&amp;", "&"))
+    } else {
+        Cow::Borrowed(value)
+    };
+    let result = cleaner.clear_url(&url).ok()?;
+    if result == url {
+        return None;
+    }
+    Some(if escaped {
+        result.replace('&', "&amp;")
+    } else {
+        result.into_owned()
+    })
+}
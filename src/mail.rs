@@ -0,0 +1,326 @@
+//! Cleaning the links in email messages.
+//!
+//! [`clean_message`] cleans the `text/plain` and `text/html` parts of a raw RFC 5322
+//! message, also in `multipart/*` and `message/rfc822` parts, and leaves everything else
+//! as it is. Links rewritten by email security gateways, like Microsoft Outlook Safe Links,
+//! are unwrapped, see the `email-rewriters` feature.
+//!
+//! ```
+//! # use clearurls::UrlCleaner;
+//! # fn main() -> Result<(), clearurls::Error> {
+//! let cleaner = UrlCleaner::from_embedded_rules()?;
+//! let message = b"Subject: Hi\r\n\r\nHave a look at https://example.com/?utm_source=x.\r\n";
+//! let cleaned = clearurls::mail::clean_message(&cleaner, message);
+//! assert_eq!(&*cleaned, b"Subject: Hi\r\n\r\nHave a look at https://example.com/.\r\n");
+//! # Ok(())
+//! # }
+//! ```
+
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+use crate::text::{clean_links, to_str, Edits};
+use crate::UrlCleaner;
+
+/// How deep `multipart/*` and `message/rfc822` parts are cleaned at most.
+const MAX_DEPTH: usize = 16;
+
+/// Clean the links in the text and HTML parts of a raw message.
+///
+/// The headers, the MIME structure and the parts without changed links are kept byte for
+/// byte. Changed parts are encoded again with their `Content-Transfer-Encoding`. Parts in
+/// charsets that aren't ASCII compatible, like UTF-16, are left unchanged.
+#[must_use]
+pub fn clean_message<'a>(cleaner: &UrlCleaner, message: &'a [u8]) -> Cow<'a, [u8]> {
+    clean_entity(cleaner, message, 0)
+}
+
+/// Clean the links in the decoded content of a `text/plain` part,
+/// like [`UrlCleaner::clean_text`].
+#[must_use]
+pub fn clean_text<'a>(cleaner: &UrlCleaner, text: &'a str) -> Cow<'a, str> {
+    cleaner.clean_text(text)
+}
+
+/// Clean the links in the decoded content of a `text/html` part.
+///
+/// Links are found like with [`UrlCleaner::clean_text`], and also end before `'`. `&amp;` in them is
+/// decoded before cleaning, and encoded again after.
+#[must_use]
+pub fn clean_html<'a>(cleaner: &UrlCleaner, html: &'a str) -> Cow<'a, str> {
+    to_str(html, clean_links(cleaner, html.as_bytes(), true))
+}
+
+fn clean_entity<'a>(cleaner: &UrlCleaner, entity: &'a [u8], depth: usize) -> Cow<'a, [u8]> {
+    let (head, body) = split_entity(entity);
+    let content_type = header(head, "content-type");
+    let content_type = content_type.as_deref().unwrap_or("text/plain");
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    let encoding = header(head, "content-transfer-encoding")
+        .map(|e| e.trim().to_ascii_lowercase())
+        .unwrap_or_default();
+    let result = if depth >= MAX_DEPTH {
+        Cow::Borrowed(body)
+    } else if mime.starts_with("multipart/") {
+        match parameter(content_type, "boundary") {
+            Some(boundary) => clean_multipart(cleaner, body, &boundary, depth),
+            None => Cow::Borrowed(body),
+        }
+    } else if mime == "message/rfc822" && !matches!(&*encoding, "base64" | "quoted-printable") {
+        clean_entity(cleaner, body, depth + 1)
+    } else if mime == "text/plain" || mime == "text/html" {
+        let charset = parameter(content_type, "charset")
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        if charset.starts_with("utf-16") || charset.starts_with("utf-32") {
+            Cow::Borrowed(body)
+        } else {
+            clean_body(cleaner, body, &encoding, mime == "text/html")
+        }
+    } else {
+        Cow::Borrowed(body)
+    };
+    let mut edits = Edits::new(entity);
+    if let Cow::Owned(body) = result {
+        edits.replace(head.len()..entity.len(), &body);
+    }
+    edits.finish()
+}
+
+/// Split an entity into its headers, including the empty line after them, and its body.
+fn split_entity(entity: &[u8]) -> (&[u8], &[u8]) {
+    let mut start = 0;
+    for line in lines(entity) {
+        if entity[line.clone()]
+            .iter()
+            .all(|b| matches!(b, b'\r' | b'\n'))
+        {
+            return entity.split_at(line.end);
+        }
+        start = line.end;
+    }
+    entity.split_at(start)
+}
+
+/// The ranges of the lines of `input`, including their line endings.
+fn lines(input: &[u8]) -> impl Iterator<Item = Range<usize>> + '_ {
+    let mut start = 0;
+    core::iter::from_fn(move || {
+        if start >= input.len() {
+            return None;
+        }
+        let end = input[start..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(input.len(), |i| start + i + 1);
+        let line = start..end;
+        start = end;
+        Some(line)
+    })
+}
+
+/// The unfolded value of the first header named `name`.
+fn header(head: &[u8], name: &str) -> Option<String> {
+    let head = String::from_utf8_lossy(head);
+    let mut value: Option<String> = None;
+    for line in head.lines() {
+        if line.starts_with([' ', '\t']) {
+            if let Some(value) = &mut value {
+                value.push_str(line);
+            }
+            continue;
+        }
+        if value.is_some() {
+            break;
+        }
+        if let Some((field, rest)) = line.split_once(':') {
+            if field.trim().eq_ignore_ascii_case(name) {
+                value = Some(rest.trim().to_string());
+            }
+        }
+    }
+    value
+}
+
+/// The value of the parameter `name` of a header like `Content-Type`.
+fn parameter(value: &str, name: &str) -> Option<String> {
+    let mut rest = value.split_once(';')?.1;
+    loop {
+        let (field, after) = rest.split_once('=')?;
+        let after = after.trim_start();
+        let (parameter, after) = if let Some(quoted) = after.strip_prefix('"') {
+            let end = quoted.find('"').unwrap_or(quoted.len());
+            (&quoted[..end], quoted.get(end + 1..).unwrap_or_default())
+        } else {
+            let end = after.find(';').unwrap_or(after.len());
+            (after[..end].trim(), &after[end..])
+        };
+        if field.trim().eq_ignore_ascii_case(name) {
+            return Some(parameter.to_string());
+        }
+        rest = after.split_once(';')?.1;
+    }
+}
+
+fn clean_multipart<'a>(
+    cleaner: &UrlCleaner,
+    body: &'a [u8],
+    boundary: &str,
+    depth: usize,
+) -> Cow<'a, [u8]> {
+    let delimiter = ["--", boundary].concat();
+    let mut edits = Edits::new(body);
+    let mut part_start = None;
+    for line in lines(body) {
+        let content = body[line.clone()].trim_ascii_end();
+        let Some(rest) = content.strip_prefix(delimiter.as_bytes()) else {
+            continue;
+        };
+        if !rest.is_empty() && rest != b"--" {
+            continue;
+        }
+        if let Some(start) = part_start {
+            // the line ending before the delimiter belongs to it, but is kept with the part
+            if let Cow::Owned(part) = clean_entity(cleaner, &body[start..line.start], depth + 1) {
+                edits.replace(start..line.start, &part);
+            }
+        }
+        if rest == b"--" {
+            break;
+        }
+        part_start = Some(line.end);
+    }
+    edits.finish()
+}
+
+fn clean_body<'a>(
+    cleaner: &UrlCleaner,
+    body: &'a [u8],
+    encoding: &str,
+    html: bool,
+) -> Cow<'a, [u8]> {
+    let line_ending: &[u8] = if body.windows(2).any(|w| w == b"\r\n") {
+        b"\r\n"
+    } else {
+        b"\n"
+    };
+    match encoding {
+        "quoted-printable" => match clean_links(cleaner, &decode_quoted_printable(body), html) {
+            Cow::Borrowed(_) => Cow::Borrowed(body),
+            Cow::Owned(text) => Cow::Owned(encode_quoted_printable(&text, line_ending)),
+        },
+        "base64" => {
+            let encoded: Vec<u8> = body
+                .iter()
+                .copied()
+                .filter(|b| !b.is_ascii_whitespace())
+                .collect();
+            let Ok(decoded) = STANDARD.decode(encoded) else {
+                return Cow::Borrowed(body);
+            };
+            match clean_links(cleaner, &decoded, html) {
+                Cow::Borrowed(_) => Cow::Borrowed(body),
+                Cow::Owned(text) => Cow::Owned(encode_base64(&text, body, line_ending)),
+            }
+        }
+        _ => clean_links(cleaner, body, html),
+    }
+}
+
+fn decode_quoted_printable(input: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(input.len());
+    let mut i = 0;
+    while i < input.len() {
+        if input[i] != b'=' {
+            output.push(input[i]);
+            i += 1;
+        } else if input[i + 1..].starts_with(b"\r\n") {
+            i += 3;
+        } else if input[i + 1..].starts_with(b"\n") {
+            i += 2;
+        } else if let Some(byte) = input
+            .get(i + 1..i + 3)
+            .and_then(|hex| core::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            output.push(byte);
+            i += 3;
+        } else {
+            output.push(b'=');
+            i += 1;
+        }
+    }
+    output
+}
+
+fn encode_quoted_printable(input: &[u8], line_ending: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(input.len() * 11 / 10);
+    for line in lines(input) {
+        let line = &input[line];
+        let (content, hard_break) = match line.strip_suffix(b"\n") {
+            Some(content) => (content.strip_suffix(b"\r").unwrap_or(content), true),
+            None => (line, false),
+        };
+        let mut length = 0;
+        for (i, &byte) in content.iter().enumerate() {
+            let last = i + 1 == content.len();
+            let literal = matches!(byte, b'!'..=b'<' | b'>'..=b'~')
+                || (matches!(byte, b' ' | b'\t') && !last);
+            let token_length = if literal { 1 } else { 3 };
+            // lines are 76 characters at most, including the `=` of soft line breaks
+            let limit = if last { 76 } else { 75 };
+            if length + token_length > limit {
+                output.push(b'=');
+                output.extend_from_slice(line_ending);
+                length = 0;
+            }
+            if literal {
+                output.push(byte);
+            } else {
+                output.extend_from_slice(&[b'=', hex(byte >> 4), hex(byte & 0xf)]);
+            }
+            length += token_length;
+        }
+        if hard_break {
+            output.extend_from_slice(line_ending);
+        }
+    }
+    output
+}
+
+fn hex(digit: u8) -> u8 {
+    b"0123456789ABCDEF"[usize::from(digit)]
+}
+
+/// Encode `input` with lines as long as the first one of `original`.
+fn encode_base64(input: &[u8], original: &[u8], line_ending: &[u8]) -> Vec<u8> {
+    let line_length = original
+        .iter()
+        .position(u8::is_ascii_whitespace)
+        .unwrap_or(original.len());
+    let line_length = match line_length / 4 * 4 {
+        0 => 76,
+        length => length,
+    };
+    let encoded = STANDARD.encode(input);
+    let mut output = Vec::with_capacity(encoded.len() * 105 / 100);
+    for (i, line) in encoded.as_bytes().chunks(line_length).enumerate() {
+        if i > 0 {
+            output.extend_from_slice(line_ending);
+        }
+        output.extend_from_slice(line);
+    }
+    let trailing = original.len() - original.trim_ascii_end().len();
+    output.extend_from_slice(&original[original.len() - trailing..]);
+    output
+}
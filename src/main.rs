@@ -0,0 +1,568 @@
+//! Clean URLs from the command line.
+//!
+//! This also runs on WASI, built with `cargo build --target wasm32-wasip1 --features cli`,
+//! except for `update-rules` and `serve`. Without a preopened cache directory, it uses the
+//! embedded rules, or the ones passed with `--rules`.
+
+use std::env;
+#[cfg(not(target_os = "wasi"))]
+use std::fs;
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+#[cfg(not(target_os = "wasi"))]
+use std::time::Duration;
+
+use clap::ArgMatches;
+use clearurls::{Report, UrlCleaner};
+use serde_json::{json, Value};
+#[cfg(not(target_os = "wasi"))]
+use sha2::{Digest, Sha256};
+
+const RULES_URL: &str = "https://rules2.clearurls.xyz/data.minify.json";
+const HASH_URL: &str = "https://rules2.clearurls.xyz/rules.minify.hash";
+/// How long lines read by `--filter` can be, in bytes. Longer ones can't be cleaned.
+const MAX_LINE_LENGTH: usize = 1 << 20;
+
+struct Args {
+    rules: Option<PathBuf>,
+    strip_referral_marketing: bool,
+    json: bool,
+    filter: bool,
+    passthrough_errors: bool,
+    urls: Vec<String>,
+}
+
+fn main() -> ExitCode {
+    let matches = command().get_matches();
+    match matches.subcommand() {
+        Some(("update-rules", matches)) => update_rules(matches),
+        Some(("serve", matches)) => serve(matches),
+        Some(("completions", matches)) => completions(matches),
+        Some(("man", _)) => man(),
+        Some(("clean", matches)) => clean(matches),
+        _ => clean(&matches),
+    }
+}
+
+/// Clean the URLs in the arguments, or the lines of standard input.
+fn clean(matches: &ArgMatches) -> ExitCode {
+    let args = Args {
+        rules: matches.get_one::<PathBuf>("rules").cloned(),
+        strip_referral_marketing: matches.get_flag("strip-referral-marketing"),
+        json: matches.get_flag("json"),
+        filter: matches.get_flag("filter"),
+        passthrough_errors: matches.get_flag("passthrough-errors"),
+        urls: matches
+            .get_many::<String>("url")
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect(),
+    };
+    let cleaner = match load_cleaner(args.rules.as_deref(), args.strip_referral_marketing) {
+        Ok(cleaner) => cleaner,
+        Err(code) => return code,
+    };
+
+    if args.filter {
+        return filter(&cleaner, &args);
+    }
+    let mut stdout = io::stdout().lock();
+    let mut failed = false;
+    let mut clean = |url: &str| {
+        let result = cleaner.clear_url_with_report(url);
+        failed |= result.is_err();
+        let written = match result {
+            Ok(report) if args.json => writeln!(stdout, "{}", to_json(url, &report)),
+            Ok(report) => writeln!(stdout, "{}", report.url),
+            Err(e) if args.json => {
+                writeln!(stdout, "{}", json!({"url": url, "error": e.to_string()}))
+            }
+            Err(e) => {
+                eprintln!("clearurls: {url}: {e}");
+                Ok(())
+            }
+        };
+        // fails like when the pipe was closed
+        written.is_ok()
+    };
+    if args.urls.is_empty() {
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else {
+                eprintln!("clearurls: standard input is not valid UTF-8");
+                return ExitCode::from(2);
+            };
+            if !line.trim().is_empty() && !clean(line.trim()) {
+                break;
+            }
+        }
+    } else {
+        for url in &args.urls {
+            if !clean(url) {
+                break;
+            }
+        }
+    }
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Load the rules from `rules`, or the downloaded or embedded ones.
+fn load_cleaner(
+    rules: Option<&Path>,
+    strip_referral_marketing: bool,
+) -> Result<UrlCleaner, ExitCode> {
+    let cached = cache_dir().map(|dir| dir.join("rules.json"));
+    let cleaner = match (rules, cached) {
+        (Some(path), _) => UrlCleaner::from_rules_path(path),
+        (None, Some(cached)) if cached.exists() => {
+            UrlCleaner::from_rules_path(&cached).or_else(|e| {
+                eprintln!(
+                    "clearurls: using the embedded rules, {} is invalid: {e}",
+                    cached.display(),
+                );
+                UrlCleaner::from_embedded_rules()
+            })
+        }
+        (None, _) => UrlCleaner::from_embedded_rules(),
+    };
+    match cleaner {
+        Ok(cleaner) => Ok(cleaner.strip_referral_marketing(strip_referral_marketing)),
+        Err(e) => {
+            eprintln!("clearurls: {e}");
+            Err(ExitCode::from(2))
+        }
+    }
+}
+
+/// Clean each line of standard input until the first error, or all with
+/// `--passthrough-errors`. Only one line is kept in memory at a time.
+fn filter(cleaner: &UrlCleaner, args: &Args) -> ExitCode {
+    let mut stdin = io::stdin().lock();
+    let mut stdout = io::BufWriter::new(io::stdout().lock());
+    let mut line = Vec::new();
+    let mut number = 0;
+    loop {
+        let too_long = match read_line(&mut stdin, &mut line) {
+            Ok(Some(too_long)) => too_long,
+            Ok(None) => break,
+            Err(e) => {
+                eprintln!("clearurls: error reading standard input: {e}");
+                return ExitCode::from(2);
+            }
+        };
+        number += 1;
+        let raw = line.strip_suffix(b"\n").unwrap_or(&line);
+        let raw = raw.strip_suffix(b"\r").unwrap_or(raw);
+        let result = match std::str::from_utf8(raw) {
+            _ if too_long => Err(format!("longer than {MAX_LINE_LENGTH} bytes")),
+            Ok(url) if url.trim().is_empty() => Ok(None),
+            Ok(url) => cleaner
+                .clear_url_with_report(url.trim())
+                .map(|report| Some((url.trim(), report)))
+                .map_err(|e| e.to_string()),
+            Err(_) => Err(String::from("not valid UTF-8")),
+        };
+        let written = match result {
+            Ok(None) => stdout.write_all(b"\n"),
+            Ok(Some((url, report))) if args.json => {
+                writeln!(stdout, "{}", to_json(url, &report))
+            }
+            Ok(Some((_, report))) => writeln!(stdout, "{}", report.url),
+            Err(e) if args.passthrough_errors && args.json => {
+                // the start of a line that is too long would be misleading
+                let url = (!too_long).then(|| String::from_utf8_lossy(raw));
+                writeln!(stdout, "{}", json!({"url": url, "error": e}))
+                    .and_then(|()| skip_line(&mut stdin, None))
+            }
+            Err(_) if args.passthrough_errors && too_long => stdout
+                .write_all(raw)
+                .and_then(|()| skip_line(&mut stdin, Some(&mut stdout))),
+            Err(_) if args.passthrough_errors => {
+                stdout.write_all(raw).and_then(|()| stdout.write_all(b"\n"))
+            }
+            Err(e) => {
+                // the lines before are complete
+                let _ = stdout.flush();
+                eprintln!("clearurls: line {number}: {e}");
+                return ExitCode::FAILURE;
+            }
+        };
+        if written.is_err() {
+            // like when the pipe was closed
+            return ExitCode::SUCCESS;
+        }
+    }
+    // a closed pipe is not an error
+    let _ = stdout.flush();
+    ExitCode::SUCCESS
+}
+
+/// Read a line into `line`, but at most [`MAX_LINE_LENGTH`] bytes of it.
+///
+/// # Returns
+/// whether the line is longer, or `None` at the end of the input
+fn read_line(input: &mut impl BufRead, line: &mut Vec<u8>) -> io::Result<Option<bool>> {
+    line.clear();
+    let limit = MAX_LINE_LENGTH as u64 + 1;
+    if Read::take(&mut *input, limit).read_until(b'\n', line)? == 0 {
+        return Ok(None);
+    }
+    let too_long = line.len() > MAX_LINE_LENGTH && !line.ends_with(b"\n");
+    if too_long {
+        line.truncate(MAX_LINE_LENGTH);
+    }
+    Ok(Some(too_long))
+}
+
+/// Skip the rest of a line that [`read_line`] stopped in, and copy it to `output` with a
+/// line ending if there is one.
+fn skip_line(input: &mut impl BufRead, mut output: Option<&mut dyn Write>) -> io::Result<()> {
+    loop {
+        let buffer = input.fill_buf()?;
+        let (rest, done) = match buffer.iter().position(|&b| b == b'\n') {
+            Some(i) => (i + 1, true),
+            None => (buffer.len(), buffer.is_empty()),
+        };
+        if let Some(output) = &mut output {
+            output.write_all(&buffer[..rest])?;
+            if buffer.is_empty() {
+                output.write_all(b"\n")?;
+            }
+        }
+        input.consume(rest);
+        if done {
+            return Ok(());
+        }
+    }
+}
+
+fn to_json(url: &str, report: &Report<'_>) -> Value {
+    let redirections: Vec<Value> = report
+        .redirections
+        .iter()
+        .map(|r| {
+            json!({
+                "provider": r.provider,
+                "pattern": r.pattern,
+                "decodes": r.decodes,
+                "target": r.target,
+            })
+        })
+        .collect();
+    let categories: Vec<_> = report
+        .categorized_parameters()
+        .map(|(_, category)| category.as_str())
+        .collect();
+    json!({
+        "url": url,
+        "cleaned": report.url,
+        "providers": report.providers,
+        "removed_parameters": report.removed_parameters,
+        "categories": categories,
+        "redirections": redirections,
+    })
+}
+
+/// The directory the rules are downloaded to.
+fn cache_dir() -> Option<PathBuf> {
+    let non_empty = |var| env::var_os(var).filter(|dir| !dir.is_empty());
+    let base = non_empty("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| non_empty("LOCALAPPDATA").map(PathBuf::from))
+        .or_else(|| non_empty("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+    Some(base.join("clearurls"))
+}
+
+fn update_rules(matches: &ArgMatches) -> ExitCode {
+    let rules_url = matches.get_one::<String>("url").expect("it has a default");
+    let hash_url = matches
+        .get_one::<String>("hash-url")
+        .expect("it has a default");
+    match download_rules(rules_url, hash_url) {
+        Ok(path) => {
+            println!("downloaded the rules to {}", path.display());
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("clearurls: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Download the rules, check them against the hash, and store them in the [`cache_dir`].
+#[cfg(not(target_os = "wasi"))]
+fn download_rules(rules_url: &str, hash_url: &str) -> Result<PathBuf, String> {
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(Duration::from_secs(60)))
+        .build()
+        .into();
+    let get = |url: &str| {
+        agent
+            .get(url)
+            .call()
+            .and_then(|mut response| response.body_mut().read_to_string())
+            .map_err(|e| format!("error downloading {url}: {e}"))
+    };
+    let rules = get(rules_url)?;
+    let expected = get(hash_url)?;
+    let expected = expected.trim();
+    let actual: String = Sha256::digest(rules.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    if !actual.eq_ignore_ascii_case(expected) {
+        return Err(format!(
+            "the rules have the SHA-256 hash {actual}, but {hash_url} has {expected}"
+        ));
+    }
+    UrlCleaner::from_rules_str(&rules).map_err(|e| format!("the rules are invalid: {e}"))?;
+
+    let dir = cache_dir().ok_or("there is no cache directory, set XDG_CACHE_HOME")?;
+    let path = dir.join("rules.json");
+    // write to another file first, so that the rules are never partially written
+    let tmp = dir.join("rules.json.tmp");
+    fs::create_dir_all(&dir)
+        .and_then(|()| fs::write(&tmp, &rules))
+        .and_then(|()| fs::rename(&tmp, &path))
+        .map_err(|e| format!("error writing {}: {e}", path.display()))?;
+    Ok(path)
+}
+
+#[cfg(target_os = "wasi")]
+fn download_rules(_: &str, _: &str) -> Result<PathBuf, String> {
+    Err("downloading the rules is not supported on WASI, use --rules instead".into())
+}
+
+fn completions(matches: &ArgMatches) -> ExitCode {
+    let shell = *matches
+        .get_one::<clap_complete::Shell>("shell")
+        .expect("it is required");
+    clap_complete::generate(shell, &mut command(), "clearurls", &mut io::stdout());
+    ExitCode::SUCCESS
+}
+
+fn man() -> ExitCode {
+    match clap_mangen::Man::new(command()).render(&mut io::stdout()) {
+        // like when the pipe was closed
+        Ok(()) | Err(_) => ExitCode::SUCCESS,
+    }
+}
+
+/// The arguments of this program, which [`main`] parses, and [`completions`] and [`man`]
+/// describe.
+fn command() -> clap::Command {
+    use clap::{value_parser, Arg, ArgAction, Command, ValueHint};
+
+    let rules = Arg::new("rules")
+        .short('r')
+        .long("rules")
+        .value_name("FILE")
+        .value_parser(value_parser!(PathBuf))
+        .value_hint(ValueHint::FilePath)
+        .help("Use the rules in FILE instead");
+    let strip_referral_marketing = Arg::new("strip-referral-marketing")
+        .short('m')
+        .long("strip-referral-marketing")
+        .action(ArgAction::SetTrue)
+        .help("Also remove referral marketing parameters");
+    let clean_args = [
+        rules.clone(),
+        strip_referral_marketing.clone(),
+        Arg::new("json")
+            .short('j')
+            .long("json")
+            .action(ArgAction::SetTrue)
+            .help(
+                "Print a JSON object per URL, with the matched providers, \
+                 removed parameters and followed redirections",
+            ),
+        Arg::new("filter")
+            .short('f')
+            .long("filter")
+            .action(ArgAction::SetTrue)
+            .conflicts_with("url")
+            .help(
+                "Clean each line of standard input, and print one line for each. \
+                 Stops at the first line that can't be cleaned",
+            ),
+        Arg::new("passthrough-errors")
+            .short('p')
+            .long("passthrough-errors")
+            .action(ArgAction::SetTrue)
+            .help(
+                "With --filter, print lines that can't be cleaned as they are, \
+                 or as an error object with --json, and continue",
+            ),
+        Arg::new("url")
+            .value_name("URL")
+            .num_args(0..)
+            .value_hint(ValueHint::Url)
+            .help("The URLs to clean, read from standard input if there are none"),
+    ];
+    Command::new("clearurls")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Remove tracking parameters from URLs")
+        .disable_help_subcommand(true)
+        // the subcommands go first, like `clearurls serve -m`
+        .args_conflicts_with_subcommands(true)
+        .long_about(
+            "Removes tracking parameters from each URL, and prints the results one per line. \
+             Without URLs, they are read from standard input, one per line.\n\n\
+             The rules downloaded by `update-rules` are used if there are any, otherwise the \
+             ones embedded in this program. They are stored in $XDG_CACHE_HOME/clearurls, or \
+             in %LOCALAPPDATA%\\clearurls if XDG_CACHE_HOME is not set, or else in \
+             ~/.cache/clearurls.",
+        )
+        .args(clean_args.clone())
+        .subcommand(
+            Command::new("clean")
+                .about("Clean URLs, which is the default")
+                .args(clean_args),
+        )
+        .subcommand(
+            Command::new("update-rules")
+                .about("Download the latest rules")
+                .arg(
+                    Arg::new("url")
+                        .long("url")
+                        .value_name("URL")
+                        .default_value(RULES_URL)
+                        .value_hint(ValueHint::Url)
+                        .help("Download the rules from URL instead of the official ones"),
+                )
+                .arg(
+                    Arg::new("hash-url")
+                        .long("hash-url")
+                        .value_name("URL")
+                        .default_value(HASH_URL)
+                        .value_hint(ValueHint::Url)
+                        .help("Download the SHA-256 hash of the rules from URL"),
+                ),
+        )
+        .subcommand(
+            Command::new("serve")
+                .about("Answer POST /clean requests with the cleaned URLs")
+                .long_about(
+                    "Answers `POST /clean` requests with a JSON array of URLs with a JSON array \
+                     of the results, like --json prints them. It needs the `serve` feature.",
+                )
+                .arg(
+                    Arg::new("listen")
+                        .long("listen")
+                        .value_name("ADDRESS")
+                        .default_value("127.0.0.1:3000")
+                        .help("Listen on ADDRESS"),
+                )
+                .arg(rules)
+                .arg(strip_referral_marketing),
+        )
+        .subcommand(
+            Command::new("completions")
+                .about("Print a completion script for bash, elvish, fish, powershell or zsh")
+                .arg(
+                    Arg::new("shell")
+                        .value_name("SHELL")
+                        .required(true)
+                        .value_parser(value_parser!(clap_complete::Shell)),
+                ),
+        )
+        .subcommand(Command::new("man").about("Print a man page"))
+}
+
+/// Answer `POST /clean` requests until the process is stopped.
+#[cfg(all(feature = "serve", not(target_os = "wasi")))]
+fn serve(matches: &ArgMatches) -> ExitCode {
+    let listen = matches
+        .get_one::<String>("listen")
+        .expect("it has a default");
+    let rules = matches.get_one::<PathBuf>("rules").map(PathBuf::as_path);
+    let strip_referral_marketing = matches.get_flag("strip-referral-marketing");
+    let cleaner = match load_cleaner(rules, strip_referral_marketing) {
+        Ok(cleaner) => cleaner,
+        Err(code) => return code,
+    };
+    let server = match tiny_http::Server::http(listen) {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("clearurls: can't listen on {listen}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Some(address) = server.server_addr().to_ip() {
+        println!("listening on http://{address}");
+    }
+    let content_type = tiny_http::Header::from_bytes("Content-Type", "application/json")
+        .expect("the header is valid");
+    for mut request in server.incoming_requests() {
+        let (status, body) = respond(&cleaner, &mut request);
+        let response = tiny_http::Response::from_string(body.to_string())
+            .with_status_code(status)
+            .with_header(content_type.clone());
+        if let Err(e) = request.respond(response) {
+            eprintln!("clearurls: error responding: {e}");
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+#[cfg(not(all(feature = "serve", not(target_os = "wasi"))))]
+fn serve(_: &ArgMatches) -> ExitCode {
+    eprintln!("clearurls: serve needs the `serve` feature, and is not supported on WASI");
+    ExitCode::from(2)
+}
+
+/// The status code and the body of the response to `request`.
+#[cfg(all(feature = "serve", not(target_os = "wasi")))]
+fn respond(cleaner: &UrlCleaner, request: &mut tiny_http::Request) -> (u16, Value) {
+    use std::io::Read;
+
+    /// The size of the largest request body that is read
+    const MAX_BODY: usize = 1 << 20;
+
+    if request.url().split('?').next() != Some("/clean") {
+        return (404, json!({"error": "not found, use POST /clean"}));
+    }
+    if *request.method() != tiny_http::Method::Post {
+        return (405, json!({"error": "method not allowed, use POST /clean"}));
+    }
+    if request
+        .body_length()
+        .is_some_and(|length| length > MAX_BODY)
+    {
+        return (413, json!({"error": "the request body is too large"}));
+    }
+    let mut body = String::new();
+    let mut reader = request.as_reader().take(MAX_BODY as u64 + 1);
+    if let Err(e) = reader.read_to_string(&mut body) {
+        return (
+            400,
+            json!({"error": format!("error reading the request body: {e}")}),
+        );
+    }
+    if body.len() > MAX_BODY {
+        return (413, json!({"error": "the request body is too large"}));
+    }
+    let urls: Vec<String> = match serde_json::from_str(&body) {
+        Ok(urls) => urls,
+        Err(e) => {
+            return (
+                400,
+                json!({"error": format!("expected a JSON array of URLs: {e}")}),
+            );
+        }
+    };
+    let results = urls
+        .iter()
+        .map(|url| match cleaner.clear_url_with_report(url) {
+            Ok(report) => to_json(url, &report),
+            Err(e) => json!({"url": url, "error": e.to_string()}),
+        })
+        .collect();
+    (200, Value::Array(results))
+}
@@ -0,0 +1,133 @@
+//! Cleaning the links in Markdown documents.
+//!
+//! [`clean_document`] parses the document with [`pulldown_cmark`](https://docs.rs/pulldown-cmark)
+//! and cleans the destinations of inline links and images, of reference definitions and of
+//! autolinks. Everything else, like code blocks, is kept byte for byte.
+//!
+//! ```
+//! # use clearurls::UrlCleaner;
+//! # fn main() -> Result<(), clearurls::Error> {
+//! let cleaner = UrlCleaner::from_embedded_rules()?;
+//! let markdown = "See [this](https://example.com/?utm_source=x), but not \
+//!                 `https://example.com/?utm_source=x`.";
+//! assert_eq!(
+//!     clearurls::markdown::clean_document(&cleaner, markdown),
+//!     "See [this](https://example.com/), but not `https://example.com/?utm_source=x`.",
+//! );
+//! # Ok(())
+//! # }
+//! ```
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use pulldown_cmark::{Event, LinkType, Options, Parser, Tag, TagEnd};
+
+use crate::text::{to_str, Edits};
+use crate::UrlCleaner;
+
+/// Clean the link destinations in a Markdown document.
+///
+/// Destinations with escapes or entities, and those that can't be cleaned, like relative URLs,
+/// are left unchanged. Parentheses in cleaned destinations are escaped if they aren't balanced.
+#[must_use]
+pub fn clean_document<'a>(cleaner: &UrlCleaner, markdown: &'a str) -> Cow<'a, str> {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS;
+    let parser = Parser::new_ext(markdown, options).into_offset_iter();
+    let mut replacements = Vec::new();
+    for (_, definition) in parser.reference_definitions().iter() {
+        let label_end = markdown[definition.span.clone()]
+            .find("]:")
+            .map_or(definition.span.start, |i| definition.span.start + i + 2);
+        let search = label_end..definition.span.end;
+        replacements.extend(clean_destination(
+            cleaner,
+            markdown,
+            search,
+            &definition.dest,
+        ));
+    }
+    // the links and images being parsed, with where their text ends
+    let mut links: Vec<(Range<usize>, LinkType, _, usize)> = Vec::new();
+    for (event, range) in parser {
+        match event {
+            Event::Start(
+                Tag::Link {
+                    link_type,
+                    dest_url,
+                    ..
+                }
+                | Tag::Image {
+                    link_type,
+                    dest_url,
+                    ..
+                },
+            ) => {
+                if link_type == LinkType::Autolink {
+                    let search = range.start + 1..range.end - 1;
+                    replacements.extend(clean_destination(cleaner, markdown, search, &dest_url));
+                }
+                links.push((range.clone(), link_type, dest_url, range.start + 1));
+            }
+            Event::End(TagEnd::Link | TagEnd::Image) => {
+                let Some((link, link_type, dest_url, text_end)) = links.pop() else {
+                    continue;
+                };
+                // the destinations of the others are in reference definitions, or aren't URLs
+                if link_type == LinkType::Inline {
+                    let search = text_end..link.end;
+                    replacements.extend(clean_destination(cleaner, markdown, search, &dest_url));
+                }
+                if let Some((.., text_end)) = links.last_mut() {
+                    *text_end = link.end.max(*text_end);
+                }
+            }
+            _ => {
+                if let Some((.., text_end)) = links.last_mut() {
+                    *text_end = range.end.max(*text_end);
+                }
+            }
+        }
+    }
+    replacements.sort_by_key(|(range, _)| range.start);
+    let mut edits = Edits::new(markdown.as_bytes());
+    for (range, destination) in replacements {
+        edits.replace(range, destination.as_bytes());
+    }
+    to_str(markdown, edits.finish())
+}
+
+/// The range of `destination` in `markdown[search]`, and its cleaned replacement, if it changed.
+fn clean_destination(
+    cleaner: &UrlCleaner,
+    markdown: &str,
+    search: Range<usize>,
+    destination: &str,
+) -> Option<(Range<usize>, String)> {
+    if destination.is_empty() {
+        return None;
+    }
+    let start = search.start + markdown.get(search)?.find(destination)?;
+    let range = start..start + destination.len();
+    let result = cleaner.clear_url(destination).ok()?;
+    if result == destination {
+        return None;
+    }
+    let in_brackets = markdown[..start].ends_with('<');
+    let balanced = result.bytes().try_fold(0_usize, |depth, b| match b {
+        b'(' => Some(depth + 1),
+        b')' => depth.checked_sub(1),
+        _ => Some(depth),
+    }) == Some(0);
+    let result = if in_brackets || balanced {
+        result.into_owned()
+    } else {
+        result.replace('(', "\\(").replace(')', "\\)")
+    };
+    Some((range, result))
+}
@@ -0,0 +1,94 @@
+//! Node.js bindings, as a native addon.
+//!
+//! ```js
+//! const { UrlCleaner } = require("./clearurls.node");
+//!
+//! const cleaner = new UrlCleaner();
+//! cleaner.clean("https://example.com/?utm_source=abc"); // "https://example.com/"
+//! await cleaner.cleanAsync("https://example.com/?utm_source=abc");
+//! ```
+//!
+//! Build it with the [napi-rs CLI](https://napi.rs/) and `--features node`.
+
+use alloc::sync::Arc;
+
+use napi::bindgen_prelude::AsyncTask;
+use napi::{Env, Task};
+use napi_derive::napi;
+// the code generated by `napi` expects the prelude and macros of `std`
+#[allow(unused_imports)]
+use std::{format, prelude::rust_2021::*, vec};
+
+use crate::{Error, UrlCleaner};
+
+/// A [`UrlCleaner`] for Node.js, which is called `UrlCleaner` there.
+#[napi(js_name = "UrlCleaner")]
+#[derive(Debug)]
+pub struct JsUrlCleaner(Arc<UrlCleaner>);
+
+#[napi]
+impl JsUrlCleaner {
+    /// Construct a cleaner with the embedded rules, or with `rules` in the JSON format of the
+    /// browser extension.
+    ///
+    /// # Errors
+    /// If the rules can't be parsed.
+    #[napi(constructor)]
+    pub fn new(rules: Option<String>) -> napi::Result<Self> {
+        let cleaner = match rules {
+            Some(rules) => UrlCleaner::from_rules_str(&rules),
+            None => UrlCleaner::from_embedded_rules(),
+        }
+        .map_err(|e| to_napi_error(&e))?;
+        Ok(Self(Arc::new(cleaner)))
+    }
+
+    /// Clean a URL, see [`UrlCleaner::clear_url`].
+    ///
+    /// # Errors
+    /// If the URL can't be cleaned.
+    #[napi]
+    #[allow(clippy::needless_pass_by_value)] // JavaScript strings are passed by value
+    pub fn clean(&self, url: String) -> napi::Result<String> {
+        clean(&self.0, &url)
+    }
+
+    /// Clean a URL on the thread pool of libuv, and resolve to the result.
+    #[napi(ts_return_type = "Promise<string>")]
+    #[must_use]
+    pub fn clean_async(&self, url: String) -> AsyncTask<CleanTask> {
+        AsyncTask::new(CleanTask {
+            cleaner: Arc::clone(&self.0),
+            url,
+        })
+    }
+}
+
+/// The task of [`JsUrlCleaner::clean_async`].
+#[derive(Debug)]
+pub struct CleanTask {
+    cleaner: Arc<UrlCleaner>,
+    url: String,
+}
+
+impl Task for CleanTask {
+    type Output = String;
+    type JsValue = String;
+
+    fn compute(&mut self) -> napi::Result<String> {
+        clean(&self.cleaner, &self.url)
+    }
+
+    fn resolve(&mut self, _: Env, output: String) -> napi::Result<String> {
+        Ok(output)
+    }
+}
+
+fn clean(cleaner: &UrlCleaner, url: &str) -> napi::Result<String> {
+    let result = cleaner.clear_url(url).map_err(|e| to_napi_error(&e))?;
+    Ok(result.into_owned())
+}
+
+fn to_napi_error(e: &Error) -> napi::Error {
+    napi::Error::from_reason(e.to_string())
+}
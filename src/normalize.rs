@@ -0,0 +1,28 @@
+use alloc::borrow::Cow;
+use alloc::string::String;
+use core::str::FromStr;
+
+use url::Url;
+
+use crate::{Error, TrailingSlash};
+
+/// Normalize a URL by serializing it with [`Url`], which lowercases the scheme and host,
+/// removes default ports and resolves dot segments. Then apply the trailing slash policy.
+pub(crate) fn normalize(url: &str, trailing_slash: TrailingSlash) -> Result<Cow<'_, str>, Error> {
+    let mut parsed = Url::from_str(url)?;
+    match trailing_slash {
+        TrailingSlash::Add if !parsed.cannot_be_a_base() && !parsed.path().ends_with('/') => {
+            let path = [parsed.path(), "/"].concat();
+            parsed.set_path(&path);
+        }
+        TrailingSlash::Remove if parsed.path().len() > 1 && parsed.path().ends_with('/') => {
+            let path = String::from(parsed.path().trim_end_matches('/'));
+            parsed.set_path(&path);
+        }
+        TrailingSlash::Keep | TrailingSlash::Add | TrailingSlash::Remove => {}
+    }
+    if parsed.as_str() == url {
+        return Ok(Cow::Borrowed(url));
+    }
+    Ok(Cow::Owned(parsed.into()))
+}
@@ -0,0 +1,119 @@
+use alloc::borrow::Cow;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use regex::Regex;
+use regex::RegexSet;
+
+use crate::Error;
+
+/// A compiled pattern from the rules.
+///
+/// Patterns are compiled with the [`regex`] crate. With the `fancy-regex` feature, patterns
+/// it rejects, like ones with lookaround or backreferences as JavaScript supports them,
+/// are compiled with `fancy_regex` instead.
+#[derive(Debug)]
+pub(crate) enum Pattern {
+    Regex(Regex),
+    #[cfg(feature = "fancy-regex")]
+    Fancy(fancy_regex::Regex),
+}
+
+impl Pattern {
+    pub(crate) fn is_match(&self, haystack: &str) -> bool {
+        match self {
+            Pattern::Regex(r) => r.is_match(haystack),
+            // errors like exceeding the backtrack limit count as no match
+            #[cfg(feature = "fancy-regex")]
+            Pattern::Fancy(r) => r.is_match(haystack).unwrap_or(false),
+        }
+    }
+
+    /// The pattern as it is written in the rules.
+    pub(crate) fn as_str(&self) -> &str {
+        match self {
+            Pattern::Regex(r) => r.as_str(),
+            #[cfg(feature = "fancy-regex")]
+            Pattern::Fancy(r) => r.as_str(),
+        }
+    }
+
+    pub(crate) fn find(&self, haystack: &str) -> Option<Range<usize>> {
+        match self {
+            Pattern::Regex(r) => r.find(haystack).map(|m| m.range()),
+            #[cfg(feature = "fancy-regex")]
+            Pattern::Fancy(r) => r.find(haystack).ok().flatten().map(|m| m.range()),
+        }
+    }
+
+    /// The ranges of all non-overlapping matches.
+    pub(crate) fn find_all(&self, haystack: &str) -> Vec<Range<usize>> {
+        match self {
+            Pattern::Regex(r) => r.find_iter(haystack).map(|m| m.range()).collect(),
+            #[cfg(feature = "fancy-regex")]
+            Pattern::Fancy(r) => r
+                .find_iter(haystack)
+                .map_while(Result::ok)
+                .map(|m| m.range())
+                .collect(),
+        }
+    }
+
+    /// Remove all matches from `haystack`.
+    pub(crate) fn remove_all<'a>(&self, haystack: &'a str) -> Cow<'a, str> {
+        match self {
+            Pattern::Regex(r) => r.replace_all(haystack, ""),
+            #[cfg(feature = "fancy-regex")]
+            Pattern::Fancy(r) => r.replace_all(haystack, ""),
+        }
+    }
+
+    /// The first capture group of the first match, if there is a match.
+    ///
+    /// # Errors
+    /// If there is a match, but the first capture group didn't participate in it.
+    /// `fancy_regex` patterns are checked to have a capture group when deserializing,
+    /// so they return `None` in this case.
+    pub(crate) fn first_group<'a>(&self, haystack: &'a str) -> Result<Option<&'a str>, Error> {
+        match self {
+            Pattern::Regex(r) => match r.captures(haystack) {
+                Some(c) => {
+                    let group = c
+                        .get(1)
+                        .ok_or_else(|| Error::RedirectionHasNoCapturingGroup(r.clone()))?;
+                    Ok(Some(group.as_str()))
+                }
+                None => Ok(None),
+            },
+            #[cfg(feature = "fancy-regex")]
+            Pattern::Fancy(r) => Ok(r
+                .captures(haystack)
+                .ok()
+                .flatten()
+                .and_then(|c| c.get(1))
+                .map(|m| m.as_str())),
+        }
+    }
+}
+
+/// A set of compiled patterns from the rules, see [`Pattern`].
+#[derive(Debug, Default)]
+pub(crate) struct PatternSet {
+    pub(crate) set: RegexSet,
+    #[cfg(feature = "fancy-regex")]
+    pub(crate) fancy: Vec<fancy_regex::Regex>,
+}
+
+impl PatternSet {
+    pub(crate) fn is_match(&self, haystack: &str) -> bool {
+        #[cfg(feature = "fancy-regex")]
+        if self
+            .fancy
+            .iter()
+            .any(|r| r.is_match(haystack).unwrap_or(false))
+        {
+            return true;
+        }
+        self.set.is_match(haystack)
+    }
+}
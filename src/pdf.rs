@@ -0,0 +1,92 @@
+//! Cleaning the URI actions of PDF documents, like the ones of link annotations, with
+//! [`lopdf`](https://docs.rs/lopdf).
+//!
+//! [`clean_pdf`] appends the changed objects to the document as an incremental update, so
+//! the original bytes, including signatures over them, are kept as they are.
+//!
+//! ```no_run
+//! # use clearurls::UrlCleaner;
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let cleaner = UrlCleaner::from_embedded_rules()?;
+//! let pdf = std::fs::read("brochure.pdf")?;
+//! std::fs::write("brochure.pdf", clearurls::pdf::clean_pdf(&cleaner, &pdf)?)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use alloc::borrow::Cow;
+use alloc::vec::Vec;
+
+use lopdf::{Dictionary, Document, IncrementalDocument, Object};
+
+use crate::UrlCleaner;
+
+/// Clean the URI actions of a PDF document, and return the document with an incremental
+/// update that contains the changed objects, or the document as it is if none changed.
+///
+/// # Errors
+/// If the document can't be parsed, or is encrypted with a password.
+pub fn clean_pdf<'a>(cleaner: &UrlCleaner, pdf: &'a [u8]) -> Result<Cow<'a, [u8]>, lopdf::Error> {
+    let mut document = IncrementalDocument::load_from(pdf)?;
+    let mut changed = Vec::new();
+    for (&id, object) in &document.get_prev_documents().objects {
+        let mut object = object.clone();
+        if clean_object(cleaner, &mut object) > 0 {
+            changed.push((id, object));
+        }
+    }
+    if changed.is_empty() {
+        return Ok(Cow::Borrowed(pdf));
+    }
+    for (id, object) in changed {
+        document.new_document.set_object(id, object);
+    }
+    let mut result = Vec::with_capacity(pdf.len());
+    document.save_to(&mut result)?;
+    Ok(Cow::Owned(result))
+}
+
+/// Clean the URI actions of a loaded PDF document, and return how many were changed.
+pub fn clean_document(cleaner: &UrlCleaner, document: &mut Document) -> usize {
+    document
+        .objects
+        .values_mut()
+        .map(|object| clean_object(cleaner, object))
+        .sum()
+}
+
+fn clean_object(cleaner: &UrlCleaner, object: &mut Object) -> usize {
+    match object {
+        Object::Dictionary(dictionary) => clean_dictionary(cleaner, dictionary),
+        Object::Stream(stream) => clean_dictionary(cleaner, &mut stream.dict),
+        Object::Array(array) => array
+            .iter_mut()
+            .map(|object| clean_object(cleaner, object))
+            .sum(),
+        _ => 0,
+    }
+}
+
+/// Clean the dictionary if it is a URI action, like `<< /S /URI /URI (https://…) >>`, and
+/// the dictionaries in it, like the action of a link annotation.
+fn clean_dictionary(cleaner: &UrlCleaner, dictionary: &mut Dictionary) -> usize {
+    let mut changed = 0;
+    if dictionary.get(b"S").and_then(Object::as_name).ok() == Some(b"URI") {
+        if let Ok(Object::String(uri, _)) = dictionary.get_mut(b"URI") {
+            let result = core::str::from_utf8(uri)
+                .ok()
+                .and_then(|uri| cleaner.clear_url(uri).ok())
+                .filter(|result| result.as_bytes() != uri.as_slice())
+                .map(|result| result.as_bytes().to_vec());
+            if let Some(result) = result {
+                *uri = result;
+                changed += 1;
+            }
+        }
+    }
+    changed
+        + dictionary
+            .iter_mut()
+            .map(|(_, object)| clean_object(cleaner, object))
+            .sum::<usize>()
+}
@@ -0,0 +1,117 @@
+//! Detection of tracking pixels and beacons by the shape of their URLs, so that mail clients
+//! can block their requests instead of cleaning them.
+//!
+//! ```
+//! # use clearurls::pixel::{detect, Pixel};
+//! assert_eq!(
+//!     detect("https://example.us1.list-manage.com/track/open.php?u=1&id=2&e=3"),
+//!     Some(Pixel::OpenTracking),
+//! );
+//! assert_eq!(detect("https://example.com/images/pixel.gif?uid=1"), Some(Pixel::SinglePixel));
+//! assert_eq!(detect("https://example.com/images/logo.png"), None);
+//! ```
+
+use url::Url;
+
+use crate::catalog::has_host;
+
+/// What kind of tracking pixel a URL is, see [`detect`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Pixel {
+    /// An endpoint of an analytics or email tracking service that only serves beacons, like
+    /// `bat.bing.com` or the `/collect` of Google Analytics
+    Beacon,
+    /// An open tracking endpoint of a newsletter, like `/track/open.php?u=…` or `/wf/open?upn=…`
+    OpenTracking,
+    /// An image of 1×1 pixels, like `pixel.gif` or `?w=1&h=1`
+    SinglePixel,
+}
+
+/// The hosts that only serve tracking pixels and beacons.
+const BEACON_HOSTS: &[&str] = &[
+    "bat.bing.com",
+    "ct.pinterest.com",
+    "mailfoogae.appspot.com",
+    "mailtrack.io",
+    "pixel.mathtag.com",
+    "pixel.quantserve.com",
+    "pixel.wp.com",
+    "px.ads.linkedin.com",
+    "sb.scorecardresearch.com",
+    "t.yesware.com",
+];
+
+/// The hosts and paths of beacon endpoints, on hosts that serve other things too.
+const BEACON_PATHS: &[(&str, &str)] = &[
+    ("facebook.com", "/tr"),
+    ("google-analytics.com", "/collect"),
+    ("google-analytics.com", "/g/collect"),
+    ("google-analytics.com", "/j/collect"),
+    ("google-analytics.com", "/__utm.gif"),
+    ("analytics.google.com", "/g/collect"),
+];
+
+/// The names of image files, without extension, that are 1×1 pixels.
+const PIXEL_NAMES: &[&str] = &[
+    "1x1",
+    "__utm",
+    "beacon",
+    "blank",
+    "clear",
+    "pixel",
+    "spacer",
+    "transparent",
+];
+
+/// The extensions of open tracking endpoints, images and scripts that return one.
+const EXTENSIONS: &[&str] = &["aspx", "gif", "jpeg", "jpg", "php", "png", "webp"];
+
+/// Whether `url` looks like a tracking pixel or beacon, and what kind of one.
+///
+/// This is a heuristic: it finds the pixels of common analytics and newsletter services, and
+/// images named like pixels, but neither every pixel nor only pixels.
+#[must_use]
+pub fn detect(url: &str) -> Option<Pixel> {
+    let parsed = Url::parse(url).ok()?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return None;
+    }
+    let path = parsed.path().to_ascii_lowercase();
+    if has_host(url, BEACON_HOSTS)
+        || BEACON_PATHS
+            .iter()
+            .any(|(host, beacon)| has_host(url, &[host]) && path.trim_end_matches('/') == *beacon)
+    {
+        return Some(Pixel::Beacon);
+    }
+    let file = path.rsplit('/').next().unwrap_or_default();
+    let (stem, extension) = match file.rsplit_once('.') {
+        Some((stem, extension)) if EXTENSIONS.contains(&extension) => (stem, Some(extension)),
+        _ => (file, None),
+    };
+    let query = parsed.query().is_some_and(|query| !query.is_empty());
+    if stem == "open" && (extension.is_some() || query) {
+        return Some(Pixel::OpenTracking);
+    }
+    let named = extension.is_some_and(|extension| extension != "php" && extension != "aspx")
+        && (PIXEL_NAMES.contains(&stem) || stem.ends_with("_1x1") || stem.ends_with("-1x1"));
+    if named || is_one_by_one(&parsed) {
+        return Some(Pixel::SinglePixel);
+    }
+    None
+}
+
+/// Whether the query asks for an image of 1×1 pixels, like `w=1&h=1` or `size=1x1`.
+fn is_one_by_one(url: &Url) -> bool {
+    let (mut width, mut height) = (false, false);
+    for (name, value) in url.query_pairs() {
+        match (&*name.to_ascii_lowercase(), &*value) {
+            ("w" | "width", "1") => width = true,
+            ("h" | "height", "1") => height = true,
+            ("size" | "dim" | "dimensions", "1x1") => return true,
+            _ => {}
+        }
+    }
+    width && height
+}
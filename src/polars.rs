@@ -0,0 +1,84 @@
+//! A [Polars](https://pola.rs) expression plugin, built into the Python module of the
+//! `python` feature.
+//!
+//! ```python
+//! import clearurls
+//! import polars as pl
+//!
+//! df.with_columns(clearurls.clean(pl.col("url")))
+//! ```
+//!
+//! URLs that can't be cleaned result in `null`. Polars applies the expression to batches
+//! of a column in parallel.
+//!
+//! The Python module must use the allocator of Polars, but a library can't choose the
+//! allocator of the programs linking it. So it is only set with `--cfg clearurls_polars_plugin`,
+//! which `pyproject.toml` passes to rustc when maturin builds the module.
+
+use alloc::string::String;
+// the expansion of `polars_expr` needs the std prelude
+use std::sync::OnceLock;
+#[allow(unused_imports)]
+use std::{format, prelude::rust_2021::*};
+
+use ::polars::prelude::*;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use pyo3_polars::derive::polars_expr;
+#[cfg(clearurls_polars_plugin)]
+use pyo3_polars::PolarsAllocator;
+use serde::Deserialize;
+
+use crate::UrlCleaner;
+
+// Series are allocated by this library and freed by Polars, and the other way round
+#[cfg(clearurls_polars_plugin)]
+#[global_allocator]
+static ALLOCATOR: PolarsAllocator = PolarsAllocator::new();
+
+#[derive(Clone, Copy, Deserialize)]
+struct CleanKwargs {
+    strip_referral_marketing: bool,
+}
+
+/// The cleaners with the embedded rules, without and with `strip_referral_marketing`.
+fn cleaner(strip_referral_marketing: bool) -> &'static UrlCleaner {
+    static CLEANERS: [OnceLock<UrlCleaner>; 2] = [OnceLock::new(), OnceLock::new()];
+    CLEANERS[usize::from(strip_referral_marketing)].get_or_init(|| {
+        UrlCleaner::from_embedded_rules()
+            .expect("the embedded rules are valid")
+            .strip_referral_marketing(strip_referral_marketing)
+    })
+}
+
+#[polars_expr(output_type = String)]
+fn clean(inputs: &[Series], kwargs: CleanKwargs) -> PolarsResult<Series> {
+    let cleaner = cleaner(kwargs.strip_referral_marketing);
+    let urls = inputs[0].str()?;
+    let result = urls.apply(|url| url.and_then(|url| cleaner.clear_url(url).ok()));
+    Ok(result.into_series())
+}
+
+/// `clean(expr, *, strip_referral_marketing=False)`: an expression that cleans the URLs
+/// of the string column `expr` with the embedded rules.
+#[pyfunction(name = "clean")]
+#[pyo3(signature = (expr, *, strip_referral_marketing = false))]
+pub(crate) fn clean_expr<'py>(
+    py: Python<'py>,
+    expr: Bound<'py, PyAny>,
+    strip_referral_marketing: bool,
+) -> PyResult<Bound<'py, PyAny>> {
+    let plugin_path: String = py.import("clearurls")?.getattr("__file__")?.extract()?;
+    let kwargs = PyDict::new(py);
+    kwargs.set_item("strip_referral_marketing", strip_referral_marketing)?;
+    let options = PyDict::new(py);
+    options.set_item("plugin_path", plugin_path)?;
+    options.set_item("function_name", "clean")?;
+    options.set_item("args", expr)?;
+    options.set_item("kwargs", kwargs)?;
+    options.set_item("is_elementwise", true)?;
+    let register = py
+        .import("polars.plugins")?
+        .getattr("register_plugin_function")?;
+    register.call((), Some(&options))
+}
@@ -0,0 +1,193 @@
+//! [`proptest`] strategies generating URLs, to property-test code that cleans them.
+//!
+//! The expected results are those of a [`UrlCleaner`][crate::UrlCleaner] with the
+//! embedded rules and the default configuration, built without the `strip-embedded-*`
+//! features.
+//!
+//! ```
+//! # use clearurls::UrlCleaner;
+//! use proptest::prelude::*;
+//!
+//! let cleaner = UrlCleaner::from_embedded_rules().unwrap();
+//! # #[cfg(not(feature = "strip-embedded-redirections"))]
+//! proptest!(|(url in clearurls::proptest::redirector_url())| {
+//!     prop_assert_eq!(cleaner.clear_url(&url.url).unwrap(), url.expected);
+//! });
+//! ```
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use ::proptest::prelude::*;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+
+/// Names of parameters that the embedded rules remove from any URL.
+const TRACKING_PARAMETERS: &[&str] = &[
+    "utm_source",
+    "utm_medium",
+    "utm_campaign",
+    "utm_term",
+    "utm_content",
+    "fbclid",
+    "gclid",
+    "dclid",
+    "msclkid",
+    "yclid",
+    "_ga",
+    "mc_eid",
+];
+
+/// Names of parameters that the embedded rules keep.
+const PARAMETERS: &[&str] = &["id", "page", "lang", "sort", "color", "size"];
+
+/// A URL and what cleaning it results in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrackedUrl {
+    /// The URL to clean
+    pub url: String,
+    /// The cleaned URL
+    pub expected: String,
+}
+
+/// Hosts like `abc.example`, that no provider applies to except the global rules.
+fn host() -> impl Strategy<Value = String> {
+    "[a-z]{1,10}(\\.[a-z]{1,10})?\\.example"
+}
+
+fn path() -> impl Strategy<Value = String> {
+    prop::collection::vec("[a-z0-9]{1,8}", 0..3).prop_map(|segments| {
+        segments
+            .iter()
+            .fold(String::new(), |path, s| path + "/" + s)
+    })
+}
+
+fn parameter() -> impl Strategy<Value = String> {
+    (prop::sample::select(PARAMETERS), "[a-z0-9]{1,8}")
+        .prop_map(|(name, value)| format!("{name}={value}"))
+}
+
+/// Parameters like `utm_source=abc`, that the embedded rules remove from any URL.
+pub fn tracking_parameter() -> impl Strategy<Value = String> {
+    (prop::sample::select(TRACKING_PARAMETERS), "[a-z0-9]{0,8}")
+        .prop_map(|(name, value)| format!("{name}={value}"))
+}
+
+/// `https` URLs without anything to clean, like `https://abc.example/a?id=1`.
+pub fn clean_url() -> impl Strategy<Value = String> {
+    (host(), path(), prop::collection::vec(parameter(), 0..4))
+        .prop_map(|(host, path, parameters)| join(&host, &path, &parameters))
+}
+
+/// URLs like [`clean_url`] with [tracking parameters][tracking_parameter] in between the
+/// others.
+pub fn tracked_url() -> impl Strategy<Value = TrackedUrl> {
+    (
+        host(),
+        path(),
+        prop::collection::vec(parameter(), 0..4),
+        prop::collection::vec(tracking_parameter(), 1..4),
+    )
+        .prop_flat_map(|(host, path, parameters, tracking)| {
+            let all: Vec<(bool, String)> = parameters
+                .into_iter()
+                .map(|p| (false, p))
+                .chain(tracking.into_iter().map(|p| (true, p)))
+                .collect();
+            (Just(host), Just(path), Just(all).prop_shuffle())
+        })
+        .prop_map(|(host, path, all)| {
+            let parameters: Vec<String> = all.iter().map(|(_, p)| p.clone()).collect();
+            let kept: Vec<String> = all
+                .into_iter()
+                .filter(|(tracking, _)| !tracking)
+                .map(|(_, p)| p)
+                .collect();
+            TrackedUrl {
+                url: join(&host, &path, &parameters),
+                expected: join(&host, &path, &kept),
+            }
+        })
+}
+
+/// [Tracked URLs][tracked_url] or [clean URLs][clean_url] wrapped in the links of known
+/// redirectors, like `https://www.google.com/url?q=https%3A%2F%2F...`, where the target
+/// may be percent-encoded more than once.
+pub fn redirector_url() -> impl Strategy<Value = TrackedUrl> {
+    let target = prop_oneof![
+        tracked_url(),
+        clean_url().prop_map(|url| TrackedUrl {
+            url: url.clone(),
+            expected: url,
+        }),
+    ];
+    (target, 0..4_usize).prop_map(|(target, shape)| {
+        let url = match shape {
+            0 => format!(
+                "https://www.google.com/url?q={}&sa=D",
+                encode(&target.url, 1)
+            ),
+            1 => format!("https://www.google.com/url?q={}", encode(&target.url, 2)),
+            2 => format!(
+                "https://www.google.de/url?sa=t&url={}&usg=abc",
+                encode(&target.url, 3)
+            ),
+            _ => format!(
+                "https://l.facebook.com/l.php?u={}&h=abc",
+                encode(&target.url, 1)
+            ),
+        };
+        TrackedUrl {
+            url,
+            expected: target.expected,
+        }
+    })
+}
+
+/// URLs with a scheme that is returned unchanged, like `mailto:` or `data:`, see
+/// [`DEFAULT_PASSTHROUGH_SCHEMES`][crate::DEFAULT_PASSTHROUGH_SCHEMES].
+pub fn passthrough_url() -> impl Strategy<Value = String> {
+    let scheme = prop::sample::select(crate::DEFAULT_PASSTHROUGH_SCHEMES);
+    (
+        scheme,
+        "[a-z0-9@.]{1,10}",
+        prop::collection::vec(tracking_parameter(), 0..3),
+    )
+        .prop_map(|(scheme, body, tracking)| {
+            if tracking.is_empty() {
+                format!("{scheme}:{body}")
+            } else {
+                format!("{scheme}:{body}?{}", tracking.join("&"))
+            }
+        })
+}
+
+/// Any of the above, or arbitrary strings, for properties that hold for any input like
+/// that cleaning never panics or is idempotent.
+pub fn any_url() -> impl Strategy<Value = String> {
+    prop_oneof![
+        clean_url(),
+        tracked_url().prop_map(|url| url.url),
+        redirector_url().prop_map(|url| url.url),
+        passthrough_url(),
+        any::<String>(),
+    ]
+}
+
+fn join(host: &str, path: &str, parameters: &[String]) -> String {
+    let path = if path.is_empty() { "/" } else { path };
+    if parameters.is_empty() {
+        format!("https://{host}{path}")
+    } else {
+        format!("https://{host}{path}?{}", parameters.join("&"))
+    }
+}
+
+fn encode(url: &str, times: usize) -> String {
+    let mut url = url.to_string();
+    for _ in 0..times {
+        url = utf8_percent_encode(&url, NON_ALPHANUMERIC).to_string();
+    }
+    url
+}
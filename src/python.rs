@@ -0,0 +1,81 @@
+//! Python bindings, as a module called `clearurls`.
+//!
+//! ```python
+//! from clearurls import UrlCleaner
+//!
+//! cleaner = UrlCleaner()
+//! cleaner.clean("https://example.com/?utm_source=abc")  # "https://example.com/"
+//! df["url"] = cleaner.clean_batch(df["url"].tolist())
+//! ```
+//!
+//! With the `polars` feature, the module also has `clean(expr, *, strip_referral_marketing=False)`,
+//! a [Polars](https://pola.rs) expression that cleans a column of URLs, like
+//! `df.with_columns(clearurls.clean(pl.col("url")))`.
+//!
+//! Build it with [maturin](https://www.maturin.rs/), which reads `pyproject.toml`.
+
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::{Error, UrlCleaner};
+
+/// A [`UrlCleaner`] for Python, which is called `UrlCleaner` there.
+#[pyclass(name = "UrlCleaner", frozen)]
+#[derive(Debug)]
+pub struct PyUrlCleaner(UrlCleaner);
+
+#[pymethods]
+impl PyUrlCleaner {
+    /// Construct a cleaner with the embedded rules, or with `rules` in the JSON format of the
+    /// browser extension.
+    ///
+    /// # Errors
+    /// `ValueError` if the rules can't be parsed.
+    #[new]
+    #[pyo3(signature = (rules = None, *, strip_referral_marketing = false))]
+    pub fn new(rules: Option<&str>, strip_referral_marketing: bool) -> PyResult<Self> {
+        let cleaner = match rules {
+            Some(rules) => UrlCleaner::from_rules_str(rules),
+            None => UrlCleaner::from_embedded_rules(),
+        }
+        .map_err(|e| to_py_err(&e))?;
+        Ok(Self(
+            cleaner.strip_referral_marketing(strip_referral_marketing),
+        ))
+    }
+
+    /// Clean a URL, see [`UrlCleaner::clear_url`].
+    ///
+    /// # Errors
+    /// `ValueError` if the URL can't be cleaned.
+    pub fn clean(&self, url: &str) -> PyResult<String> {
+        let cleaned = self.0.clear_url(url).map_err(|e| to_py_err(&e))?;
+        Ok(cleaned.into_owned())
+    }
+
+    /// Clean many URLs, without holding the GIL. URLs that can't be cleaned result in `None`,
+    /// so that a column with some invalid URLs can be cleaned at once.
+    #[must_use]
+    pub fn clean_batch(&self, py: Python<'_>, urls: Vec<String>) -> Vec<Option<String>> {
+        py.detach(|| {
+            urls.into_iter()
+                .map(|url| self.0.clear_url(&url).ok().map(Cow::into_owned))
+                .collect()
+        })
+    }
+}
+
+fn to_py_err(e: &Error) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+#[pymodule]
+fn clearurls(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    #[cfg(feature = "polars")]
+    m.add_function(wrap_pyfunction!(crate::polars::clean_expr, m)?)?;
+    m.add_class::<PyUrlCleaner>()
+}
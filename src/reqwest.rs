@@ -0,0 +1,96 @@
+//! Middleware for [`reqwest_middleware`], that cleans the URL of each request before it's
+//! sent.
+//!
+//! ```
+//! # use std::sync::Arc;
+//! # use clearurls::UrlCleaner;
+//! # use clearurls::reqwest::CleanUrlMiddleware;
+//! # fn main() -> Result<(), clearurls::Error> {
+//! let cleaner = Arc::new(UrlCleaner::from_embedded_rules()?);
+//! let client = reqwest_middleware::ClientBuilder::new(reqwest::Client::new())
+//!     .with(CleanUrlMiddleware::new(cleaner))
+//!     .build();
+//! # Ok(())
+//! # }
+//! ```
+
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+
+use ::reqwest::header::{HeaderValue, LOCATION};
+use ::reqwest::{Request, Response, Url};
+use http::Extensions;
+use reqwest_middleware::{Middleware, Next, Result};
+
+use crate::UrlCleaner;
+
+/// A [`Middleware`] that cleans the URL of each request with a [`UrlCleaner`].
+///
+/// Redirections are followed like with [`UrlCleaner::clear_url`], so a request to a
+/// wrapper URL goes to its target instead, unless the cleaner is configured with
+/// [`UrlCleaner::max_redirects`]`(0)`. URLs that can't be cleaned are sent unchanged.
+#[derive(Debug, Clone)]
+pub struct CleanUrlMiddleware {
+    cleaner: Arc<UrlCleaner>,
+    clean_locations: bool,
+}
+
+impl CleanUrlMiddleware {
+    /// Construct a middleware that cleans URLs with `cleaner`.
+    #[must_use]
+    pub fn new(cleaner: Arc<UrlCleaner>) -> Self {
+        Self {
+            cleaner,
+            clean_locations: false,
+        }
+    }
+
+    /// Configure whether the `Location` header of redirect responses is cleaned too.
+    ///
+    /// This only applies to redirects the middleware sees, so the client must not follow
+    /// them itself, like with [`reqwest::redirect::Policy::none`]. A relative location is
+    /// replaced by the cleaned absolute URL. The default is `false`.
+    #[must_use]
+    pub fn clean_locations(mut self, value: bool) -> Self {
+        self.clean_locations = value;
+        self
+    }
+
+    fn clean(&self, url: &Url) -> Option<Url> {
+        match self.cleaner.clear_url(url.as_str()).ok()? {
+            Cow::Borrowed(_) => None,
+            Cow::Owned(result) => Url::parse(&result).ok(),
+        }
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for CleanUrlMiddleware {
+    async fn handle(
+        &self,
+        mut request: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        if let Some(url) = self.clean(request.url()) {
+            *request.url_mut() = url;
+        }
+        let url = request.url().clone();
+        let mut response = next.run(request, extensions).await?;
+        if self.clean_locations && response.status().is_redirection() {
+            let cleaned = response
+                .headers()
+                .get(LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .and_then(|location| url.join(location).ok())
+                .and_then(|location| self.clean(&location))
+                .and_then(|location| HeaderValue::from_str(location.as_str()).ok());
+            if let Some(cleaned) = cleaned {
+                response.headers_mut().insert(LOCATION, cleaned);
+            }
+        }
+        Ok(response)
+    }
+}
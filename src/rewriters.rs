@@ -0,0 +1,95 @@
+//! Decoders for links rewritten by email security gateways, which encode the original URL
+//! in ways that redirection regexes can't express.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use base64::alphabet::URL_SAFE;
+use base64::engine::general_purpose::NO_PAD;
+use base64::engine::{DecodePaddingMode, GeneralPurpose};
+use base64::Engine;
+use percent_encoding::percent_decode_str;
+use url::Url;
+
+/// The URL wrapped in `url` with the name of the gateway, if it is a Microsoft Outlook
+/// Safe Links or a Proofpoint URL Defense link.
+pub(crate) fn unwrap(url: &str) -> Option<(&'static str, String)> {
+    let parsed = Url::parse(url).ok()?;
+    let host = parsed.host_str()?;
+    if host.ends_with(".safelinks.protection.outlook.com") {
+        return query_param(&parsed, "url").map(|target| ("Safe Links", target));
+    }
+    let target = match (host, parsed.path()) {
+        ("urldefense.proofpoint.com", "/v1/url") => query_param(&parsed, "u"),
+        ("urldefense.proofpoint.com", "/v2/url") => {
+            // `-` stands for `%` and `_` for `/`
+            let encoded: String = query_param(&parsed, "u")?
+                .chars()
+                .map(|c| match c {
+                    '-' => '%',
+                    '_' => '/',
+                    c => c,
+                })
+                .collect();
+            let decoded = percent_decode_str(&encoded).decode_utf8().ok()?;
+            Some(decoded.into_owned())
+        }
+        ("urldefense.com", path) if path.starts_with("/v3/__") => decode_v3(url),
+        _ => None,
+    };
+    target.map(|target| ("URL Defense", target))
+}
+
+fn query_param(url: &Url, name: &str) -> Option<String> {
+    url.query_pairs()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned())
+}
+
+/// Like [`base64::engine::general_purpose::URL_SAFE_NO_PAD`], but accepting padding and
+/// trailing bits.
+const LENIENT_URL_SAFE: GeneralPurpose = GeneralPurpose::new(
+    &URL_SAFE,
+    NO_PAD
+        .with_decode_padding_mode(DecodePaddingMode::Indifferent)
+        .with_decode_allow_trailing_bits(true),
+);
+
+/// The characters after `**` in a URL Defense v3 link, in the order of the lengths they stand for.
+const RUN_LENGTHS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Decode a link like `https://urldefense.com/v3/__https://example.com/*a__;Pw!!id$`.
+///
+/// The URL is between `__` and `__;`, with some characters replaced by `*`, which are
+/// base64-encoded after the `;`. `**` followed by a character stands for as many replaced
+/// characters in a row as the position of the character in the base64url alphabet, plus 2.
+fn decode_v3(url: &str) -> Option<String> {
+    let start = url.find("/v3/__")? + "/v3/__".len();
+    let rest = &url[start..];
+    let end = rest.find("__;")?;
+    let encoded = percent_decode_str(&rest[..end]).decode_utf8().ok()?;
+    let replacements = &rest[end + "__;".len()..];
+    let replacements = &replacements[..replacements.find('!').unwrap_or(replacements.len())];
+    let replacements = LENIENT_URL_SAFE.decode(replacements).ok()?;
+    let replacements: Vec<char> = String::from_utf8(replacements).ok()?.chars().collect();
+
+    let mut decoded = String::with_capacity(encoded.len());
+    let mut next = 0;
+    let mut chars = encoded.chars();
+    while let Some(c) = chars.next() {
+        if c != '*' {
+            decoded.push(c);
+            continue;
+        }
+        let len = if chars.as_str().starts_with('*') {
+            chars.next();
+            let run = chars.next()?;
+            RUN_LENGTHS.find(run)? + 2
+        } else {
+            1
+        };
+        decoded.extend(replacements.get(next..next + len)?);
+        next += len;
+    }
+    Some(decoded)
+}
@@ -1,100 +1,269 @@
 use alloc::borrow::Cow;
 use alloc::str::FromStr;
 use alloc::string::String;
-use alloc::string::ToString;
 use alloc::vec::Vec;
+use core::ops::Range;
 
+#[cfg(feature = "base64-redirections")]
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+#[cfg(feature = "base64-redirections")]
+use base64::Engine;
 use percent_encoding::percent_decode_str;
-use regex::{Regex, RegexSet};
 use serde::Deserialize;
 use url::{form_urlencoded, Url};
 
 use crate::deserialize_utils::{
-    deserialize_map_as_vec, deserialize_regex, deserialize_regex_set, deserialize_regex_vec,
+    deserialize_map_as_vec, deserialize_pattern, deserialize_pattern_set, deserialize_pattern_vec,
+    deserialize_redirections,
+};
+use crate::pattern::{Pattern, PatternSet};
+use crate::{
+    Config, DefaultRedirectScheme, Error, FragmentPolicy, HashInQuery, NonUtf8RedirectPolicy,
+    UnsafeRedirectPolicy,
 };
-use crate::Error;
 
 #[derive(Debug, Deserialize)]
 pub(crate) struct Rules {
+    /// The providers with their names
     #[serde(deserialize_with = "deserialize_map_as_vec")]
-    pub(crate) providers: Vec<Provider>,
+    pub(crate) providers: Vec<(String, Provider)>,
 }
 
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub(crate) struct Provider {
-    #[serde(deserialize_with = "deserialize_regex")]
-    url_pattern: Regex,
-    #[serde(default, deserialize_with = "deserialize_regex_vec")]
-    rules: Vec<Regex>,
-    #[serde(default, deserialize_with = "deserialize_regex_vec")]
-    raw_rules: Vec<Regex>,
-    #[serde(default, deserialize_with = "deserialize_regex_vec")]
-    referral_marketing: Vec<Regex>,
-    #[serde(default, deserialize_with = "deserialize_regex_set")]
-    exceptions: RegexSet,
-    #[serde(default, deserialize_with = "deserialize_regex_vec")]
-    redirections: Vec<Regex>,
+    #[serde(deserialize_with = "deserialize_pattern")]
+    url_pattern: Pattern,
+    /// Match `url_pattern` against the host of the URL only, like `example.com`,
+    /// instead of the whole URL. Exceptions are still matched against the whole URL.
+    #[serde(default)]
+    host_only: bool,
+    #[serde(default, deserialize_with = "deserialize_pattern_vec")]
+    rules: Vec<Pattern>,
+    #[serde(default, deserialize_with = "deserialize_pattern_vec")]
+    raw_rules: Vec<Pattern>,
+    #[serde(default, deserialize_with = "deserialize_pattern_vec")]
+    referral_marketing: Vec<Pattern>,
+    #[serde(default, deserialize_with = "deserialize_pattern_set")]
+    exceptions: PatternSet,
+    #[serde(default, deserialize_with = "deserialize_redirections")]
+    redirections: Vec<Pattern>,
+    /// Like `redirections`, but the target is encoded with base64 or base64url.
+    /// Always empty without the `base64-redirections` feature.
+    #[cfg_attr(not(feature = "base64-redirections"), serde(skip))]
+    #[serde(default, deserialize_with = "deserialize_redirections")]
+    base64_redirections: Vec<Pattern>,
+}
+
+/// The result of applying a [`Provider`] to a URL.
+pub(crate) struct Cleaned<'a> {
+    pub(crate) url: Cow<'a, str>,
+    /// Whether the `rawRules` of the provider changed the URL.
+    pub(crate) raw_rules_applied: bool,
+    /// Where `url` came from, if it is the target of a redirection.
+    pub(crate) redirected: Option<Origin>,
+    /// The query and fragment parameters that were removed, as they were written.
+    pub(crate) removed: Vec<String>,
+}
+
+/// How the target of a redirection was found.
+pub(crate) struct Origin {
+    /// The redirection pattern that matched
+    pub(crate) pattern: String,
+    /// How often the target was decoded
+    pub(crate) decodes: usize,
 }
 
 impl Provider {
+    /// `matching_url` is the form of `url` that was passed to [`Provider::match_url`],
+    /// see [`url_for_matching`]. Redirections are detected on it if `follow_redirections`
+    /// is set, edits are applied to `url`.
     pub(crate) fn remove_fields_from_url<'a>(
         &self,
         url: &'a str,
-        strip_referral_marketing: bool,
-    ) -> Result<Cow<'a, str>, Error> {
-        if let Some(redirect) = self.get_redirection(url)? {
-            let url = repeatedly_urldecode(redirect)?;
-            return Ok(url);
-        };
+        matching_url: &str,
+        config: &Config,
+        follow_redirections: bool,
+    ) -> Result<Cleaned<'a>, Error> {
+        if follow_redirections {
+            if let Some((redirect, origin)) = self.redirect(url, matching_url, config)? {
+                return Ok(Cleaned {
+                    url: Cow::Owned(redirect),
+                    raw_rules_applied: false,
+                    redirected: Some(origin),
+                    removed: Vec::new(),
+                });
+            }
+        }
         let mut url = Cow::Borrowed(url);
         for r in &self.raw_rules {
-            match r.replace_all(&url, "") {
+            match r.remove_all(&url) {
                 Cow::Borrowed(_) => {}
                 Cow::Owned(new) => url = Cow::Owned(new),
             }
+            if config.decoded_raw_rules {
+                if let Cow::Owned(new) = remove_decoded_matches(r, &url) {
+                    url = Cow::Owned(new);
+                }
+            }
+        }
+        let raw_rules_applied = matches!(url, Cow::Owned(_));
+        if raw_rules_applied && config.reevaluate_after_raw_rules && follow_redirections {
+            let matching_url = url_for_matching(&url, config);
+            if let Some((redirect, origin)) = self.redirect(&url, &matching_url, config)? {
+                return Ok(Cleaned {
+                    url: Cow::Owned(redirect),
+                    raw_rules_applied,
+                    redirected: Some(origin),
+                    removed: Vec::new(),
+                });
+            }
         }
-        // clones the string
-        let mut url = Url::from_str(&url)?;
-        let mut fields: Vec<(Cow<'_, str>, Cow<'_, str>)> = url.query_pairs().collect();
-        let fragments = url.fragment().unwrap_or("");
-        let mut fragments: Vec<(Cow<'_, str>, Cow<'_, str>)> =
-            form_urlencoded::parse(fragments.as_bytes()).collect();
+        // only validates, the output is spliced from the original string
+        Url::from_str(&url)?;
+        let rules: Vec<&Pattern> = self.get_rules(config.strip_referral_marketing).collect();
 
-        for r in self.get_rules(strip_referral_marketing) {
-            fields.retain(|(k, _)| !is_full_match(r, k));
-            fragments.retain(|(k, _)| !is_full_match(r, k));
+        let (before_fragment, fragment) = match fragment_start(&url, config) {
+            Some(i) => (&url[..i], Some(&url[i + 1..])),
+            None => (&*url, None),
+        };
+        let (base, query) = match before_fragment.split_once('?') {
+            Some((base, query)) => (base, Some(query)),
+            None => (before_fragment, None),
+        };
+        let mut removed = Vec::new();
+        let query = match query.and_then(|q| remove_params(q, &rules, &mut removed)) {
+            Some(q) if config.hash_in_query == HashInQuery::Escape && q.contains('#') => {
+                Some(Cow::Owned(q.replace('#', "%23")))
+            }
+            query => query,
+        };
+        let fragment = fragment.and_then(|f| match config.fragment_policy {
+            // nested URLs are cleaned as a whole by `UrlCleaner`
+            _ if is_nested_url(f) => Some(Cow::Borrowed(f)),
+            FragmentPolicy::Never => Some(Cow::Borrowed(f)),
+            FragmentPolicy::Pairs if !f.split('&').all(|p| p.is_empty() || p.contains('=')) => {
+                Some(Cow::Borrowed(f))
+            }
+            FragmentPolicy::Pairs | FragmentPolicy::Always => {
+                remove_params(f, &rules, &mut removed)
+            }
+        });
+        let (query, fragment) = if config.remove_empty_query_and_fragment {
+            (
+                query.filter(|q| !q.is_empty()),
+                fragment.filter(|f| !f.is_empty()),
+            )
+        } else {
+            (query, fragment)
+        };
+
+        let mut result = String::with_capacity(url.len());
+        result.push_str(base);
+        if let Some(query) = query {
+            result.push('?');
+            result.push_str(&query);
+        }
+        if let Some(fragment) = fragment {
+            result.push('#');
+            result.push_str(&fragment);
         }
-        let query = serialize_params(fields.iter());
-        let fragment = serialize_params(fragments.iter());
-        url.set_query(query.as_deref());
-        url.set_fragment(fragment.as_deref());
+        let url = if result == *url {
+            // nothing was changed
+            url
+        } else {
+            Cow::Owned(result)
+        };
+        Ok(Cleaned {
+            url,
+            raw_rules_applied,
+            redirected: None,
+            removed,
+        })
+    }
 
-        Ok(Cow::Owned(url.to_string())) // I'm sad about the allocation
+    /// Remove the parameters of a form-urlencoded body, like `a=1&utm_source=x`, that the
+    /// rules of this provider match, and add them to `removed`. Raw rules only apply to URLs.
+    pub(crate) fn remove_fields_from_body<'a>(
+        &self,
+        body: &'a str,
+        config: &Config,
+        removed: &mut Vec<String>,
+    ) -> Cow<'a, str> {
+        let rules: Vec<&Pattern> = self.get_rules(config.strip_referral_marketing).collect();
+        remove_params(body, &rules, removed).unwrap_or_default()
     }
 
     pub(crate) fn match_url(&self, url: &str) -> bool {
-        self.url_pattern.is_match(url) && !self.match_exception(url)
+        let matched = if self.host_only {
+            let host =
+                authority_range(url).map_or("", |authority| &url[host_range(url, authority)]);
+            self.url_pattern.is_match(host)
+        } else {
+            self.url_pattern.is_match(url)
+        };
+        matched && !self.match_exception(url)
     }
 
     fn match_exception(&self, url: &str) -> bool {
         url == "javascript:void(0)" || self.exceptions.is_match(url)
     }
 
-    fn get_redirection<'a>(&self, url: &'a str) -> Result<Option<&'a str>, Error> {
-        for r in &self.redirections {
-            if let Some(c) = r.captures(url) {
-                let c = c
-                    .get(1)
-                    .ok_or_else(|| Error::RedirectionHasNoCapturingGroup(r.clone()))?;
-                let s = c.as_str();
-                return Ok(Some(s));
+    /// The decoded target of the first redirection that matches, if any.
+    /// A relative target is resolved against `url`.
+    fn redirect(
+        &self,
+        url: &str,
+        matching_url: &str,
+        config: &Config,
+    ) -> Result<Option<(String, Origin)>, Error> {
+        let lossy = config.non_utf8_redirect_policy == NonUtf8RedirectPolicy::Lossy;
+        let (pattern, result) =
+            if let Some((pattern, redirect)) = first_group(&self.redirections, matching_url)? {
+                let decoded = repeatedly_urldecode(redirect, lossy, config.max_redirect_decodes);
+                (pattern, decoded)
+            } else if let Some((pattern, redirect)) =
+                first_group(&self.base64_redirections, matching_url)?
+            {
+                match base64_decode(redirect, lossy) {
+                    Some(decoded) => (pattern, decoded.map(|target| (target, 1))),
+                    // not base64, so this is no redirection
+                    None => return Ok(None),
+                }
+            } else {
+                return Ok(None);
+            };
+        match result {
+            Ok((found, decodes)) => {
+                let target = resolve_target(&found, url, config.default_redirect_scheme);
+                if !check_target(&found, &target, config)? {
+                    return Ok(None);
+                }
+                let origin = Origin {
+                    pattern: pattern.as_str().into(),
+                    decodes,
+                };
+                Ok(Some((target, origin)))
+            }
+            Err(Error::PercentDecodeUtf8Error(_))
+                if config.non_utf8_redirect_policy == NonUtf8RedirectPolicy::Ignore =>
+            {
+                Ok(None)
             }
+            Err(e) => Err(e),
         }
-        Ok(None)
     }
 
-    fn get_rules(&self, strip_referral_marketing: bool) -> impl Iterator<Item = &Regex> {
+    /// Whether one of the redirection patterns matches `matching_url`, see
+    /// [`Provider::remove_fields_from_url`].
+    pub(crate) fn match_redirection(&self, matching_url: &str) -> bool {
+        self.redirections
+            .iter()
+            .chain(&self.base64_redirections)
+            .any(|r| r.is_match(matching_url))
+    }
+
+    fn get_rules(&self, strip_referral_marketing: bool) -> impl Iterator<Item = &Pattern> {
         if strip_referral_marketing {
             self.rules.iter().chain(self.referral_marketing.iter())
         } else {
@@ -103,33 +272,334 @@ impl Provider {
     }
 }
 
-fn serialize_params<'a>(mut params: impl Iterator<Item=&'a (Cow<'a, str>, Cow<'a, str>)>) -> Option<String> {
-    let first2: Vec<_> = params.by_ref().take(2).collect();
-    let ret = match &first2[..] {
-        [] => String::new(),
-        [anchor] if anchor.1 == "" => anchor.0.clone().into_owned(),
-        _ => {
-            form_urlencoded::Serializer::new(String::new()).extend_pairs(first2).extend_pairs(params).finish()
-        }
+/// The form of a URL that provider patterns are matched against.
+///
+/// Patterns are written for `scheme://host/...`, so userinfo and port are left out of the
+/// authority, the trailing dot of an absolute domain like `example.com.` is removed, and
+/// [https-like schemes][crate::UrlCleaner::https_like_schemes] are replaced by `https`.
+/// URLs without an authority are returned unchanged.
+pub(crate) fn url_for_matching<'a>(url: &'a str, config: &Config) -> Cow<'a, str> {
+    let Some(authority) = authority_range(url) else {
+        return Cow::Borrowed(url);
+    };
+    let mut host = host_range(url, authority.clone());
+    if host.len() > 1 && url[host.clone()].ends_with('.') {
+        host.end -= 1;
+    }
+    let scheme = scheme(url).filter(|scheme| config.is_https_like(scheme));
+    if host == authority && scheme.is_none() {
+        return Cow::Borrowed(url);
+    }
+    let prefix = if scheme.is_some() {
+        "https://"
+    } else {
+        &url[..authority.start]
     };
-    Some(ret).filter(|r| !r.is_empty())
+    Cow::Owned([prefix, &url[host], &url[authority.end..]].concat())
 }
 
-fn repeatedly_urldecode(s: &str) -> Result<Cow<'_, str>, Error> {
-    let mut before = Cow::Borrowed(s);
+/// The scheme of a URL, without the colon.
+pub(crate) fn scheme(url: &str) -> Option<&str> {
+    let (scheme, _) = url.split_once(':')?;
+    let mut chars = scheme.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    valid.then_some(scheme)
+}
+
+/// The index of the `#` that starts the fragment of `url`, if it has one.
+///
+/// With [`HashInQuery::Keep`] or [`HashInQuery::Escape`], a `#` followed by `&` before any
+/// `=`, like in `?q=C#&lang=en`, belongs to the query.
+pub(crate) fn fragment_start(url: &str, config: &Config) -> Option<usize> {
+    let mut start = url.find('#')?;
+    if config.hash_in_query == HashInQuery::Fragment || !url[..start].contains('?') {
+        return Some(start);
+    }
     loop {
-        let after = percent_decode_str(s).decode_utf8()?;
-        if after == before {
-            return if after.starts_with("http") {
-                Ok(after)
+        let end = url[start + 1..]
+            .find('#')
+            .map_or(url.len(), |i| start + 1 + i);
+        let in_query = url[start + 1..end]
+            .split_once('&')
+            .is_some_and(|(before, _)| !before.contains('='));
+        if !in_query {
+            return Some(start);
+        }
+        if end == url.len() {
+            return None;
+        }
+        start = end;
+    }
+}
+
+/// Whether a fragment is a URL with an authority, like `https://example.com/?x`.
+pub(crate) fn is_nested_url(fragment: &str) -> bool {
+    scheme(fragment).is_some_and(|scheme| fragment[scheme.len()..].starts_with("://"))
+}
+
+/// The byte range of the authority (`userinfo@host:port`) of a URL, if it has one.
+pub(crate) fn authority_range(url: &str) -> Option<Range<usize>> {
+    let start = url.find("://")? + "://".len();
+    let end = url[start..]
+        .find(['/', '?', '#'])
+        .map_or(url.len(), |i| start + i);
+    Some(start..end)
+}
+
+/// The byte range of the host inside the `authority` range of `url`.
+pub(crate) fn host_range(url: &str, authority: Range<usize>) -> Range<usize> {
+    let authority_str = &url[authority.clone()];
+    let start = authority_str.rfind('@').map_or(0, |i| i + 1);
+    // a colon inside brackets belongs to an IPv6 address
+    let end = match authority_str.rfind(':') {
+        Some(i) if i >= start && !authority_str[i..].contains(']') => i,
+        _ => authority_str.len(),
+    };
+    authority.start + start..authority.start + end
+}
+
+/// Remove the matches of `regex` in a view of `url` where percent-encoded ASCII is decoded.
+/// The matches are mapped back to and removed from the original `url`.
+fn remove_decoded_matches<'a>(regex: &Pattern, url: &'a str) -> Cow<'a, str> {
+    if !url.contains('%') {
+        return Cow::Borrowed(url);
+    }
+    let mut decoded = String::with_capacity(url.len());
+    // `offsets[i]` is the index in `url` where the `i`-th byte of `decoded` comes from
+    let mut offsets = Vec::with_capacity(url.len() + 1);
+    let mut chars = url.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let byte = url
+            .get(i + 1..i + 3)
+            .filter(|hex| c == '%' && hex.bytes().all(|b| b.is_ascii_hexdigit()))
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            .filter(u8::is_ascii);
+        offsets.push(i);
+        if let Some(byte) = byte {
+            decoded.push(char::from(byte));
+            chars.next();
+            chars.next();
+        } else {
+            offsets.extend(core::iter::repeat_n(i, c.len_utf8() - 1));
+            decoded.push(c);
+        }
+    }
+    offsets.push(url.len());
+
+    let mut result = String::new();
+    let mut copied_until = 0;
+    for m in regex
+        .find_all(&decoded)
+        .into_iter()
+        .filter(|m| !m.is_empty())
+    {
+        let start = offsets[m.start];
+        result.push_str(&url[copied_until..start]);
+        copied_until = offsets[m.end];
+    }
+    if copied_until == 0 {
+        return Cow::Borrowed(url);
+    }
+    result.push_str(&url[copied_until..]);
+    Cow::Owned(result)
+}
+
+/// Remove the `&`-separated parameters whose name fully matches one of the rules.
+///
+/// Retained parameters are copied verbatim, so their encoding (`+` or `%20`, the case of
+/// percent-encodings, ...) stays the same. Empty parameters are removed as well.
+/// Removed parameters other than empty ones are added to `removed`.
+/// Returns `None` if parameters were removed and none are left, an empty `params` is
+/// returned as is.
+///
+/// A further `?` inside a parameter, like in `x=1?utm_source=feed`, is kept as part of
+/// the value, but `key=value` pairs following it are removed if they match a rule.
+fn remove_params<'a>(
+    params: &'a str,
+    rules: &[&Pattern],
+    removed: &mut Vec<String>,
+) -> Option<Cow<'a, str>> {
+    if params.is_empty() {
+        return Some(Cow::Borrowed(params));
+    }
+    let mut retained = Vec::new();
+    let mut changed = false;
+    for param in params.split('&') {
+        let mut parts = param.split('?');
+        let first = parts.next().unwrap_or_default();
+        if param.is_empty() || is_removed_param(first, rules) {
+            if !param.is_empty() {
+                removed.push(param.into());
+            }
+            changed = true;
+            continue;
+        }
+        let mut kept = String::from(first);
+        for part in parts {
+            if part.contains('=') && is_removed_param(part, rules) {
+                removed.push(part.into());
+                changed = true;
             } else {
-                Ok(Cow::Owned(["http://", &*after].join("")))
-            };
+                kept.push('?');
+                kept.push_str(part);
+            }
+        }
+        retained.push(kept);
+    }
+    if !changed {
+        return Some(Cow::Borrowed(params));
+    }
+    let retained = retained.join("&");
+    (!retained.is_empty()).then_some(Cow::Owned(retained))
+}
+
+fn is_removed_param(param: &str, rules: &[&Pattern]) -> bool {
+    // rules are matched against the decoded name, and also against the name as written
+    // since some upstream rules contain percent-encodings, like `(?:%3F)?utm_source`
+    let raw_name = param.split_once('=').map_or(param, |(name, _)| name);
+    let name = form_urlencoded::parse(raw_name.as_bytes())
+        .next()
+        .map_or(Cow::Borrowed(""), |(name, _)| name);
+    rules
+        .iter()
+        .any(|r| is_full_match(r, &name) || is_full_match(r, raw_name))
+}
+
+/// The first capture group of the first of `patterns` that matches `url`.
+fn first_group<'p, 'a>(
+    patterns: &'p [Pattern],
+    url: &'a str,
+) -> Result<Option<(&'p Pattern, &'a str)>, Error> {
+    for r in patterns {
+        if let Some(s) = r.first_group(url)? {
+            return Ok(Some((r, s)));
+        }
+    }
+    Ok(None)
+}
+
+/// Decode a redirection target once, and again while its start is still percent-encoded,
+/// like `https%3A%2F%2F…`, at most `max_decodes` times. Decoding stops as soon as the scheme
+/// or host can be read, so that the encoding of URLs nested in the target is kept for the
+/// next redirection. Returns how often the target was decoded. Bytes that are invalid UTF-8
+/// result in an error, or are replaced with `U+FFFD` if `lossy` is set.
+fn repeatedly_urldecode(
+    s: &str,
+    lossy: bool,
+    max_decodes: usize,
+) -> Result<(Cow<'_, str>, usize), Error> {
+    let mut url = Cow::Borrowed(s);
+    let mut count = 0;
+    loop {
+        let decoded = percent_decode_str(&url);
+        let after = if lossy {
+            decoded.decode_utf8_lossy()
+        } else {
+            decoded.decode_utf8()?
+        };
+        if after == url {
+            return Ok((url, count));
+        }
+        if count == max_decodes {
+            return Err(Error::RedirectionTooDeeplyEncoded(max_decodes));
+        }
+        count += 1;
+        url = Cow::Owned(after.into_owned());
+        if !starts_encoded(&url) {
+            return Ok((url, count));
         }
-        before = after;
     }
 }
 
-fn is_full_match(regex: &Regex, haystack: &str) -> bool {
+/// Whether `s` is percent-encoded before its first `:`, `/`, `?` or `#`, like
+/// `https%3A%2F%2Fexample.com`, so that its scheme and host can't be read yet.
+fn starts_encoded(s: &str) -> bool {
+    s.find(['%', ':', '/', '?', '#'])
+        .is_some_and(|i| s[i..].starts_with('%'))
+}
+
+/// Decode a redirection target encoded with base64 or base64url, with or without padding,
+/// and possibly percent-encoded on top. Returns `None` if `s` is not base64.
+#[cfg(feature = "base64-redirections")]
+fn base64_decode(s: &str, lossy: bool) -> Option<Result<Cow<'static, str>, Error>> {
+    let s: String = percent_decode_str(s)
+        .decode_utf8_lossy()
+        .trim_end_matches('=')
+        .chars()
+        .map(|c| match c {
+            '+' => '-',
+            '/' => '_',
+            c => c,
+        })
+        .collect();
+    let bytes = URL_SAFE_NO_PAD.decode(s).ok()?;
+    let decoded = if lossy {
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    } else {
+        String::from_utf8(bytes).map_err(|e| Error::from(e.utf8_error()))
+    };
+    Some(decoded.map(Cow::Owned))
+}
+
+#[cfg(not(feature = "base64-redirections"))]
+fn base64_decode(_: &str, _: bool) -> Option<Result<Cow<'static, str>, Error>> {
+    None
+}
+
+/// Make a redirection target absolute.
+///
+/// `http` and `https` URLs are returned as they are. Scheme-relative targets like
+/// `//example.com/a` and path-relative ones starting with `/`, `.`, `?` or `#` are resolved
+/// against `base`, the URL containing the redirection. Other targets without a scheme, like
+/// `example.com/a`, get one according to the [`DefaultRedirectScheme`].
+fn resolve_target(target: &str, base: &str, default_scheme: DefaultRedirectScheme) -> String {
+    if Url::parse(target).is_ok_and(|url| matches!(url.scheme(), "http" | "https")) {
+        return String::from(target);
+    }
+    if target.starts_with(['/', '.', '?', '#']) {
+        if let Ok(resolved) = Url::parse(base).and_then(|base| base.join(target)) {
+            return resolved.into();
+        }
+    }
+    let scheme = match default_scheme {
+        DefaultRedirectScheme::Http => "http",
+        DefaultRedirectScheme::Https => "https",
+        DefaultRedirectScheme::Inherit => scheme(base).unwrap_or("http"),
+    };
+    [scheme, "://", target].concat()
+}
+
+/// Check the target of a redirection with the [`UnsafeRedirectPolicy`]. `decoded` is the
+/// target as it was found, and `resolved` is the absolute URL it resolves to.
+///
+/// # Returns
+/// whether to follow the redirection
+pub(crate) fn check_target(decoded: &str, resolved: &str, config: &Config) -> Result<bool, Error> {
+    if config.unsafe_redirect_policy == UnsafeRedirectPolicy::Allow {
+        return Ok(true);
+    }
+    // `example.com:8080/a` has a port, not a scheme
+    let unsafe_scheme = scheme(decoded).is_some_and(|s| {
+        let is_port = decoded[s.len() + 1..].starts_with(|c: char| c.is_ascii_digit());
+        let is_safe = s.eq_ignore_ascii_case("http")
+            || s.eq_ignore_ascii_case("https")
+            || config.is_https_like(s);
+        !(is_port || is_safe)
+    });
+    // targets that can't be parsed result in an error later
+    let has_userinfo = Url::parse(resolved)
+        .is_ok_and(|url| !url.username().is_empty() || url.password().is_some());
+    if !(unsafe_scheme || has_userinfo) {
+        return Ok(true);
+    }
+    match config.unsafe_redirect_policy {
+        UnsafeRedirectPolicy::Ignore => Ok(false),
+        _ if unsafe_scheme => Err(Error::UnsafeRedirectionTarget(decoded.into())),
+        _ => Err(Error::UnsafeRedirectionTarget(resolved.into())),
+    }
+}
+
+fn is_full_match(regex: &Pattern, haystack: &str) -> bool {
     regex.find(haystack).is_some_and(|m| m.len() == haystack.len())
 }
\ No newline at end of file
@@ -0,0 +1,46 @@
+use alloc::borrow::Cow;
+use alloc::string::String;
+
+/// Clean up a URL that was pasted from text, see [`UrlCleaner::sanitize_input`].
+///
+/// Zero-width characters are removed, then surrounding whitespace and punctuation that
+/// likely belongs to the sentence around the URL, like `.` or an unbalanced `)`.
+///
+/// [`UrlCleaner::sanitize_input`]: crate::UrlCleaner::sanitize_input
+pub(crate) fn sanitize(url: &str) -> Cow<'_, str> {
+    let url = if url.contains(is_invisible) {
+        Cow::Owned(
+            url.chars()
+                .filter(|c| !is_invisible(*c))
+                .collect::<String>(),
+        )
+    } else {
+        Cow::Borrowed(url)
+    };
+    match url {
+        Cow::Borrowed(url) => Cow::Borrowed(trim(url)),
+        Cow::Owned(url) => Cow::Owned(String::from(trim(&url))),
+    }
+}
+
+fn trim(mut url: &str) -> &str {
+    url = url.trim();
+    loop {
+        let trimmed = url.trim_end_matches(['.', ',', ':', ';', '!', '?', '\'', '"']);
+        let trimmed = match trimmed.strip_suffix(')') {
+            Some(rest) if trimmed.matches('(').count() < trimmed.matches(')').count() => rest,
+            _ => trimmed,
+        };
+        if trimmed.len() == url.len() {
+            return url;
+        }
+        url = trimmed;
+    }
+}
+
+fn is_invisible(c: char) -> bool {
+    matches!(
+        c,
+        '\u{00AD}' | '\u{200B}' | '\u{200C}' | '\u{200D}' | '\u{2060}' | '\u{FEFF}'
+    )
+}
@@ -0,0 +1,79 @@
+//! Cleaning the URLs of sitemaps and sitemap indexes, and finding the ones that changed, like
+//! tracking parameters that leaked into canonical URLs.
+//!
+//! ```
+//! # use clearurls::UrlCleaner;
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let cleaner = UrlCleaner::from_embedded_rules()?;
+//! let xml = "<urlset><url><loc>https://example.com/?utm_source=x</loc></url></urlset>";
+//! let sitemap = clearurls::sitemap::clean_sitemap(&cleaner, xml)?;
+//! assert_eq!(sitemap.xml, "<urlset><url><loc>https://example.com/</loc></url></urlset>");
+//! assert_eq!(sitemap.changes[0].original, "https://example.com/?utm_source=x");
+//! # Ok(())
+//! # }
+//! ```
+
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+use crate::xml::{clean_url, local_name};
+use crate::UrlCleaner;
+
+/// A cleaned sitemap, see [`clean_sitemap`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Sitemap<'a> {
+    /// The sitemap with the cleaned URLs
+    pub xml: Cow<'a, str>,
+    /// The URLs that changed, in the order of the sitemap
+    pub changes: Vec<Change>,
+}
+
+/// A URL of a sitemap that changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Change {
+    /// The URL in the sitemap
+    pub original: String,
+    /// The cleaned URL
+    pub cleaned: String,
+}
+
+/// Clean the `loc`s of a sitemap or a sitemap index, also those of image and video extensions,
+/// and the `href`s of alternate `xhtml:link`s. Everything else is kept byte for byte.
+///
+/// # Errors
+/// If the sitemap isn't well-formed XML.
+pub fn clean_sitemap<'a>(
+    cleaner: &UrlCleaner,
+    xml: &'a str,
+) -> Result<Sitemap<'a>, quick_xml::Error> {
+    let changes = RefCell::new(Vec::new());
+    let clean = |url: &str| {
+        let result = clean_url(cleaner, url)?;
+        changes.borrow_mut().push(Change {
+            original: url.trim().to_string(),
+            cleaned: result.trim().to_string(),
+        });
+        Some(result)
+    };
+    let xml = crate::xml::rewrite(
+        xml,
+        |element, attribute, value| match (local_name(element), attribute) {
+            ("link", "href") => clean(value),
+            _ => None,
+        },
+        |element, text| {
+            let element = local_name(element);
+            (element == "loc" || element.ends_with("_loc"))
+                .then(|| clean(text))
+                .flatten()
+        },
+    )?;
+    Ok(Sitemap {
+        xml,
+        changes: changes.into_inner(),
+    })
+}
@@ -0,0 +1,191 @@
+//! Finding and cleaning the links in text.
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::UrlCleaner;
+
+impl UrlCleaner {
+    /// Clean each link in `text`, and keep everything else as it is.
+    ///
+    /// Links start with `http://` or `https://`, and end before whitespace, characters that
+    /// can't be in a URL, like `<`, `"` or `|`, and Unicode punctuation, like `。`. They end
+    /// before punctuation at their end too, like the period of a sentence or a closing
+    /// parenthesis without an opening one in the link. Links that can't be cleaned are left
+    /// unchanged.
+    ///
+    /// ```
+    /// # use clearurls::UrlCleaner;
+    /// # fn main() -> Result<(), clearurls::Error> {
+    /// let cleaner = UrlCleaner::from_embedded_rules()?;
+    /// let text = "Have a look (https://example.com/?utm_source=x).";
+    /// assert_eq!(cleaner.clean_text(text), "Have a look (https://example.com/).");
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn clean_text<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        to_str(text, clean_links(self, text.as_bytes(), false))
+    }
+}
+
+pub(crate) fn to_str<'a>(input: &'a str, cleaned: Cow<'_, [u8]>) -> Cow<'a, str> {
+    match cleaned {
+        Cow::Borrowed(_) => Cow::Borrowed(input),
+        // only whole links, which are valid UTF-8, are replaced
+        Cow::Owned(cleaned) => Cow::Owned(String::from_utf8_lossy(&cleaned).into_owned()),
+    }
+}
+
+/// Replacements of ranges of an input, which is only copied if there is one.
+pub(crate) struct Edits<'a> {
+    input: &'a [u8],
+    output: Option<Vec<u8>>,
+    copied: usize,
+}
+
+impl<'a> Edits<'a> {
+    pub(crate) fn new(input: &'a [u8]) -> Self {
+        Self {
+            input,
+            output: None,
+            copied: 0,
+        }
+    }
+
+    pub(crate) fn replace(&mut self, range: Range<usize>, with: &[u8]) {
+        let output = self.output.get_or_insert_with(Vec::new);
+        output.extend_from_slice(&self.input[self.copied..range.start]);
+        output.extend_from_slice(with);
+        self.copied = range.end;
+    }
+
+    pub(crate) fn finish(self) -> Cow<'a, [u8]> {
+        match self.output {
+            Some(mut output) => {
+                output.extend_from_slice(&self.input[self.copied..]);
+                Cow::Owned(output)
+            }
+            None => Cow::Borrowed(self.input),
+        }
+    }
+}
+
+/// Clean the `http://` and `https://` links in `text`, see [`UrlCleaner::clean_text`]
+/// and [`crate::mail::clean_html`] for `html`.
+pub(crate) fn clean_links<'a>(cleaner: &UrlCleaner, text: &'a [u8], html: bool) -> Cow<'a, [u8]> {
+    let mut edits = Edits::new(text);
+    let mut position = 0;
+    while let Some(start) = find_link(text, position) {
+        let end = link_end(text, start, html);
+        position = end;
+        let Ok(link) = core::str::from_utf8(&text[start..end]) else {
+            continue;
+        };
+        let escaped = html && link.contains("&amp;");
+        let url = if escaped {
+            Cow::Owned(link.replace("&amp;", "&"))
+        } else {
+            Cow::Borrowed(link)
+        };
+        let Ok(result) = cleaner.clear_url(&url) else {
+            continue;
+        };
+        if result != url {
+            let result = if escaped {
+                result.replace('&', "&amp;")
+            } else {
+                result.into_owned()
+            };
+            edits.replace(start..end, result.as_bytes());
+        }
+    }
+    edits.finish()
+}
+
+/// The start of the next `http://` or `https://` link from `position` on.
+fn find_link(text: &[u8], position: usize) -> Option<usize> {
+    (position..text.len()).find(|&i| {
+        let rest = &text[i..];
+        let scheme = rest.len() >= 7
+            && (rest[..7].eq_ignore_ascii_case(b"http://")
+                || rest.len() >= 8 && rest[..8].eq_ignore_ascii_case(b"https://"));
+        scheme && (i == 0 || !text[i - 1].is_ascii_alphanumeric())
+    })
+}
+
+/// The end of the link starting at `start`: the first character that can't be in a URL, like
+/// whitespace, `<` or `|`, or that is Unicode punctuation, like `。`, and without punctuation
+/// at its end that is more likely part of the text.
+fn link_end(text: &[u8], start: usize, html: bool) -> usize {
+    let mut end = start;
+    while let Some(&b) = text.get(end) {
+        if b.is_ascii() {
+            if b.is_ascii_whitespace()
+                || b.is_ascii_control()
+                || matches!(
+                    b,
+                    b'<' | b'>' | b'"' | b'\\' | b'^' | b'`' | b'{' | b'|' | b'}'
+                )
+                || (html && b == b'\'')
+            {
+                break;
+            }
+            end += 1;
+            continue;
+        }
+        let len = match b {
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            _ => 4,
+        };
+        // bytes that are invalid UTF-8 are kept in the link, which isn't cleaned then
+        match text
+            .get(end..end + len)
+            .and_then(|c| core::str::from_utf8(c).ok())
+            .and_then(|c| c.chars().next())
+        {
+            Some(c) if c.is_whitespace() || is_punctuation(c) => break,
+            Some(c) => end += c.len_utf8(),
+            None => end += 1,
+        }
+    }
+    // punctuation at the end is more likely part of the text around the link
+    loop {
+        let link = &text[start..end];
+        let depth = link.iter().fold(0_isize, |depth, b| match b {
+            b'(' => depth + 1,
+            b')' => depth - 1,
+            _ => depth,
+        });
+        match link.last() {
+            Some(b'.' | b',' | b';' | b':' | b'!' | b'?' | b'\'') => end -= 1,
+            Some(b')') if depth < 0 => end -= 1,
+            _ => return end,
+        }
+    }
+}
+
+/// Whether `c` is non-ASCII punctuation that ends a link, like `。`, `、` or `«`. Other
+/// characters, like letters, are kept in the link, which is an IRI then.
+fn is_punctuation(c: char) -> bool {
+    matches!(c,
+        '\u{a1}' | '\u{ab}' | '\u{b7}' | '\u{bb}' | '\u{bf}'
+        // General Punctuation, like `…`, `“` and zero-width spaces
+        | '\u{2000}'..='\u{206f}'
+        // Supplemental Punctuation
+        | '\u{2e00}'..='\u{2e7f}'
+        // CJK Symbols and Punctuation, like `、`, `。` and `「`
+        | '\u{3000}'..='\u{303f}'
+        // Vertical Forms, CJK Compatibility Forms and Small Form Variants
+        | '\u{fe10}'..='\u{fe1f}'
+        | '\u{fe30}'..='\u{fe6f}'
+        // the fullwidth forms of ASCII punctuation, like `，` and `）`
+        | '\u{ff01}'..='\u{ff0f}'
+        | '\u{ff1a}'..='\u{ff20}'
+        | '\u{ff3b}'..='\u{ff40}'
+        | '\u{ff5b}'..='\u{ff65}'
+    )
+}
@@ -0,0 +1,109 @@
+//! Middleware for [`tower`](https://docs.rs/tower) based HTTP clients, like the ones of
+//! `hyper`, that cleans the URI of each request before it's sent.
+//!
+//! ```
+//! # use std::sync::Arc;
+//! # use clearurls::UrlCleaner;
+//! # use clearurls::tower::CleanUrlLayer;
+//! # fn main() -> Result<(), clearurls::Error> {
+//! let layer = CleanUrlLayer::new(Arc::new(UrlCleaner::from_embedded_rules()?));
+//! // let client = tower::ServiceBuilder::new().layer(layer).service(client);
+//! # Ok(())
+//! # }
+//! ```
+
+use alloc::borrow::Cow;
+use alloc::string::ToString;
+use alloc::sync::Arc;
+use core::task::{Context, Poll};
+
+use http::{Request, Uri};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::UrlCleaner;
+
+/// A [`Layer`] that wraps services in [`CleanUrl`].
+#[derive(Debug, Clone)]
+pub struct CleanUrlLayer {
+    cleaner: Arc<UrlCleaner>,
+}
+
+impl CleanUrlLayer {
+    /// Construct a layer that cleans URIs with `cleaner`.
+    #[must_use]
+    pub fn new(cleaner: Arc<UrlCleaner>) -> Self {
+        Self { cleaner }
+    }
+}
+
+impl<S> Layer<S> for CleanUrlLayer {
+    type Service = CleanUrl<S>;
+
+    fn layer(&self, inner: S) -> CleanUrl<S> {
+        CleanUrl {
+            inner,
+            cleaner: Arc::clone(&self.cleaner),
+        }
+    }
+}
+
+/// A [`Service`] that cleans the URI of each request with a [`UrlCleaner`], and passes it on
+/// to the inner service.
+///
+/// Redirections are followed like with [`UrlCleaner::clear_url`], so a request to a
+/// wrapper URL goes to its target instead, unless the cleaner is configured with
+/// [`UrlCleaner::max_redirects`]`(0)`. URIs without a scheme and host, and URIs that can't
+/// be cleaned, are passed on unchanged.
+#[derive(Debug, Clone)]
+pub struct CleanUrl<S> {
+    inner: S,
+    cleaner: Arc<UrlCleaner>,
+}
+
+impl<S> CleanUrl<S> {
+    /// Wrap `inner` to clean URIs with `cleaner`.
+    pub fn new(inner: S, cleaner: Arc<UrlCleaner>) -> Self {
+        Self { inner, cleaner }
+    }
+
+    /// The wrapped service.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Unwrap the service.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S, B> Service<Request<B>> for CleanUrl<S>
+where
+    S: Service<Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<B>) -> S::Future {
+        if let Some(uri) = clean_uri(&self.cleaner, request.uri()) {
+            *request.uri_mut() = uri;
+        }
+        self.inner.call(request)
+    }
+}
+
+/// The cleaned form of `uri`, if it's absolute and was changed.
+fn clean_uri(cleaner: &UrlCleaner, uri: &Uri) -> Option<Uri> {
+    uri.scheme()?;
+    let uri = uri.to_string();
+    match cleaner.clear_url(&uri).ok()? {
+        Cow::Borrowed(_) => None,
+        Cow::Owned(result) => Uri::try_from(result).ok(),
+    }
+}
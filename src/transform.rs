@@ -0,0 +1,92 @@
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+
+use crate::{Error, UrlCleaner};
+
+/// Something that rewrites URLs, like a [`UrlCleaner`].
+///
+/// Code that accepts any `UrlTransformer` instead of a [`UrlCleaner`] lets its users
+/// combine the cleaner with their own steps, see [`UrlTransformer::then`].
+///
+/// ```
+/// # use std::borrow::Cow;
+/// # use clearurls::{Error, UrlCleaner, UrlTransformer};
+/// /// Use HTTPS for example.com
+/// struct Upgrade;
+///
+/// impl UrlTransformer for Upgrade {
+///     fn transform<'a>(&self, url: &'a str) -> Result<Cow<'a, str>, Error> {
+///         Ok(match url.strip_prefix("http://example.com") {
+///             Some(rest) => Cow::Owned(format!("https://example.com{rest}")),
+///             None => Cow::Borrowed(url),
+///         })
+///     }
+/// }
+///
+/// # fn main() -> Result<(), Error> {
+/// let transformer = UrlCleaner::from_embedded_rules()?.then(Upgrade);
+/// let result = transformer.transform("http://example.com/a?utm_source=x")?;
+/// assert_eq!(result, "https://example.com/a");
+/// # Ok(())
+/// # }
+/// ```
+pub trait UrlTransformer {
+    /// Rewrite `url`, returning it borrowed if it's unchanged.
+    ///
+    /// # Errors
+    /// If the URL can't be rewritten. See the [`Error`] enum for possible reasons.
+    fn transform<'a>(&self, url: &'a str) -> Result<Cow<'a, str>, Error>;
+
+    /// A transformer that applies this one first, and then `next` to the result.
+    #[must_use]
+    fn then<T: UrlTransformer>(self, next: T) -> Then<Self, T>
+    where
+        Self: Sized,
+    {
+        Then {
+            first: self,
+            second: next,
+        }
+    }
+}
+
+impl UrlTransformer for UrlCleaner {
+    fn transform<'a>(&self, url: &'a str) -> Result<Cow<'a, str>, Error> {
+        self.clear_url(url)
+    }
+}
+
+impl<T: UrlTransformer + ?Sized> UrlTransformer for &T {
+    fn transform<'a>(&self, url: &'a str) -> Result<Cow<'a, str>, Error> {
+        (**self).transform(url)
+    }
+}
+
+impl<T: UrlTransformer + ?Sized> UrlTransformer for Box<T> {
+    fn transform<'a>(&self, url: &'a str) -> Result<Cow<'a, str>, Error> {
+        (**self).transform(url)
+    }
+}
+
+impl<T: UrlTransformer + ?Sized> UrlTransformer for Arc<T> {
+    fn transform<'a>(&self, url: &'a str) -> Result<Cow<'a, str>, Error> {
+        (**self).transform(url)
+    }
+}
+
+/// Two [`UrlTransformer`]s applied one after the other, see [`UrlTransformer::then`].
+#[derive(Debug, Clone)]
+pub struct Then<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A: UrlTransformer, B: UrlTransformer> UrlTransformer for Then<A, B> {
+    fn transform<'a>(&self, url: &'a str) -> Result<Cow<'a, str>, Error> {
+        match self.first.transform(url)? {
+            Cow::Borrowed(url) => self.second.transform(url),
+            Cow::Owned(url) => Ok(Cow::Owned(self.second.transform(&url)?.into_owned())),
+        }
+    }
+}
@@ -0,0 +1,78 @@
+//! Bindings for Swift and Kotlin with [UniFFI](https://mozilla.github.io/uniffi-rs).
+//!
+//! Build the library with `cargo rustc --lib --crate-type cdylib --release --features uniffi`,
+//! or `--crate-type staticlib` for iOS, and generate the bindings from it with the
+//! `uniffi-bindgen` binary of the `uniffi-bindgen` feature.
+
+use alloc::string::String;
+use alloc::sync::Arc;
+use core::fmt::{Display, Formatter};
+
+use crate::Error;
+
+/// The [`UrlCleaner`][crate::UrlCleaner] of the bindings.
+#[derive(Debug, ::uniffi::Object)]
+pub struct UrlCleaner(crate::UrlCleaner);
+
+/// Why a [`UrlCleaner`] couldn't be constructed, or a URL couldn't be cleaned.
+#[derive(Debug, ::uniffi::Error)]
+#[uniffi(flat_error)]
+#[non_exhaustive]
+pub enum CleanError {
+    /// The rules are invalid
+    InvalidRules(Error),
+    /// The URL can't be cleaned
+    InvalidUrl(Error),
+}
+
+impl Display for CleanError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            CleanError::InvalidRules(e) => write!(f, "the rules are invalid: {e}"),
+            CleanError::InvalidUrl(e) => write!(f, "the URL can't be cleaned: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for CleanError {}
+
+#[::uniffi::export]
+impl UrlCleaner {
+    /// A cleaner with the rules embedded in this library.
+    ///
+    /// # Errors
+    /// If the embedded rules are invalid.
+    #[uniffi::constructor]
+    pub fn new(strip_referral_marketing: bool) -> Result<Arc<Self>, CleanError> {
+        let cleaner = crate::UrlCleaner::from_embedded_rules().map_err(CleanError::InvalidRules)?;
+        Ok(Arc::new(Self(
+            cleaner.strip_referral_marketing(strip_referral_marketing),
+        )))
+    }
+
+    /// A cleaner with the rules in the JSON string `rules`.
+    ///
+    /// # Errors
+    /// If the rules are invalid.
+    #[uniffi::constructor]
+    pub fn from_rules(
+        rules: &str,
+        strip_referral_marketing: bool,
+    ) -> Result<Arc<Self>, CleanError> {
+        let cleaner = crate::UrlCleaner::from_rules_str(rules).map_err(CleanError::InvalidRules)?;
+        Ok(Arc::new(Self(
+            cleaner.strip_referral_marketing(strip_referral_marketing),
+        )))
+    }
+
+    /// Clean `url`, see [`crate::UrlCleaner::clear_url`].
+    ///
+    /// # Errors
+    /// If the URL can't be cleaned.
+    pub fn clean(&self, url: &str) -> Result<String, CleanError> {
+        match self.0.clear_url(url) {
+            Ok(cleaned) => Ok(cleaned.into_owned()),
+            Err(e) => Err(CleanError::InvalidUrl(e)),
+        }
+    }
+}
@@ -0,0 +1,178 @@
+//! Streaming the records of WARC web archives, and cleaning their target URIs, like for
+//! deduplicating captures across crawls.
+//!
+//! Only the headers of records are kept in memory, their content is skipped. Compressed
+//! archives (`.warc.gz`) have to be decompressed first, like with `flate2::bufread::MultiGzDecoder`.
+//!
+//! ```
+//! # use clearurls::UrlCleaner;
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let cleaner = UrlCleaner::from_embedded_rules()?;
+//! let warc = b"WARC/1.1\r\nWARC-Type: response\r\n\
+//!              WARC-Target-URI: https://example.com/?utm_source=x\r\n\
+//!              Content-Length: 4\r\n\r\nbody\r\n\r\n";
+//! for target in clearurls::warc::targets(&cleaner, &warc[..]) {
+//!     println!("{}", target?.to_json());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use std::io::{self, BufRead, Read};
+
+use serde::Serialize;
+
+use crate::UrlCleaner;
+
+/// Iterate over the records of a WARC archive in `reader` that have a `WARC-Target-URI`.
+pub fn targets<R: BufRead>(cleaner: &UrlCleaner, reader: R) -> Targets<'_, R> {
+    Targets {
+        cleaner,
+        reader,
+        done: false,
+    }
+}
+
+/// The target URI of a WARC record, and its cleaned version.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[non_exhaustive]
+pub struct Target {
+    /// The `WARC-Record-ID`, like `<urn:uuid:…>`
+    pub record_id: Option<String>,
+    /// The `WARC-Type`, like `response`
+    pub record_type: Option<String>,
+    /// The `WARC-Target-URI`
+    pub uri: String,
+    /// The cleaned target URI, if cleaning changed it
+    pub cleaned: Option<String>,
+}
+
+impl Target {
+    /// The URI to deduplicate captures by, the cleaned one if there is one.
+    #[must_use]
+    pub fn canonical_uri(&self) -> &str {
+        self.cleaned.as_deref().unwrap_or(&self.uri)
+    }
+
+    /// The target as a single line of JSON.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// An iterator over the [`Target`]s of a WARC archive, see [`targets`].
+///
+/// If a record is malformed, like one without a `Content-Length`, an error of the kind
+/// [`io::ErrorKind::InvalidData`] is returned, and the iteration ends.
+#[derive(Debug)]
+pub struct Targets<'a, R> {
+    cleaner: &'a UrlCleaner,
+    reader: R,
+    done: bool,
+}
+
+impl<R: BufRead> Iterator for Targets<'_, R> {
+    type Item = io::Result<Target>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            match self.read_record() {
+                Ok(Some(headers)) => {
+                    if let Some(target) = self.target(&headers) {
+                        return Some(Ok(target));
+                    }
+                }
+                Ok(None) => self.done = true,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+        None
+    }
+}
+
+impl<R: BufRead> Targets<'_, R> {
+    /// Read the headers of the next record, and skip its content.
+    fn read_record(&mut self) -> io::Result<Option<Vec<(String, String)>>> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            if !line.trim().is_empty() {
+                break;
+            }
+        }
+        if !line.starts_with("WARC/") {
+            return Err(invalid("a record doesn't start with a WARC version"));
+        }
+        let mut headers: Vec<(String, String)> = Vec::new();
+        loop {
+            line.clear();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            let content = line.trim_end_matches(['\r', '\n']);
+            if content.is_empty() {
+                break;
+            }
+            if content.starts_with([' ', '\t']) {
+                if let Some((_, value)) = headers.last_mut() {
+                    if !value.is_empty() {
+                        value.push(' ');
+                    }
+                    value.push_str(content.trim());
+                }
+            } else if let Some((name, value)) = content.split_once(':') {
+                headers.push((name.trim().to_string(), value.trim().to_string()));
+            }
+        }
+        let length = header(&headers, "Content-Length")
+            .and_then(|length| length.parse::<u64>().ok())
+            .ok_or_else(|| invalid("a record has no valid Content-Length"))?;
+        let skipped = io::copy(&mut (&mut self.reader).take(length), &mut io::sink())?;
+        if skipped < length {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(Some(headers))
+    }
+
+    fn target(&self, headers: &[(String, String)]) -> Option<Target> {
+        let uri = header(headers, "WARC-Target-URI")?;
+        // WARC 1.0 puts the URI in angle brackets
+        let uri = uri
+            .strip_prefix('<')
+            .and_then(|uri| uri.strip_suffix('>'))
+            .unwrap_or(uri);
+        let cleaned = self
+            .cleaner
+            .clear_url(uri)
+            .ok()
+            .filter(|cleaned| cleaned != uri)
+            .map(Cow::into_owned);
+        Some(Target {
+            record_id: header(headers, "WARC-Record-ID").map(ToString::to_string),
+            record_type: header(headers, "WARC-Type").map(ToString::to_string),
+            uri: uri.to_string(),
+            cleaned,
+        })
+    }
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(header, _)| header.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,207 @@
+//! JavaScript bindings, for web apps and browser extensions.
+//!
+//! The embedded rules are included in the module. Build it with
+//! `cargo rustc --lib --crate-type cdylib --target wasm32-unknown-unknown --release --features wasm`,
+//! and generate the JavaScript glue code with `wasm-bindgen` from the resulting `clearurls.wasm`.
+//!
+//! ```js
+//! const cleaner = new UrlCleaner();
+//! cleaner.clean("https://example.com/?utm_source=abc"); // "https://example.com/"
+//! cleaner.stats().total; // 1
+//! ```
+//!
+//! Each cleaner counts what it did, see [`JsUrlCleaner::stats`]. For counts per page, like the
+//! badge of the browser extension, keep a `Stats` for each page and record the reports of
+//! `cleanWithReport` in it.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::Cell;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{Redirection, Report, Stats, UrlCleaner};
+
+/// A [`UrlCleaner`] for JavaScript, which is called `UrlCleaner` there.
+#[wasm_bindgen(js_name = UrlCleaner)]
+#[derive(Debug)]
+pub struct JsUrlCleaner {
+    cleaner: UrlCleaner,
+    stats: Cell<Stats>,
+}
+
+#[wasm_bindgen(js_class = UrlCleaner)]
+impl JsUrlCleaner {
+    /// Construct a cleaner with the embedded rules, see [`UrlCleaner::from_embedded_rules`].
+    ///
+    /// # Errors
+    /// If the embedded rules can't be parsed.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Result<JsUrlCleaner, JsError> {
+        Ok(UrlCleaner::from_embedded_rules()?.into())
+    }
+
+    /// Construct a cleaner with rules in the JSON format of the browser extension,
+    /// see [`UrlCleaner::from_rules_str`].
+    ///
+    /// # Errors
+    /// If the rules can't be parsed.
+    #[wasm_bindgen(js_name = fromRules)]
+    pub fn from_rules(rules: &str) -> Result<JsUrlCleaner, JsError> {
+        Ok(UrlCleaner::from_rules_str(rules)?.into())
+    }
+
+    /// Clean a URL, see [`UrlCleaner::clear_url`].
+    ///
+    /// # Errors
+    /// If the URL can't be cleaned.
+    pub fn clean(&self, url: &str) -> Result<String, JsError> {
+        Ok(self.clean_and_record(url)?.url.into_owned())
+    }
+
+    /// Clean a URL, and report the redirections that were followed,
+    /// see [`UrlCleaner::clear_url_with_report`].
+    ///
+    /// # Errors
+    /// If the URL can't be cleaned.
+    #[wasm_bindgen(js_name = cleanWithReport)]
+    pub fn clean_with_report(&self, url: &str) -> Result<JsReport, JsError> {
+        let report = self.clean_and_record(url)?;
+        Ok(JsReport {
+            url: report.url.into_owned(),
+            providers: report.providers,
+            removed_parameters: report.removed_parameters,
+            redirections: report
+                .redirections
+                .into_iter()
+                .map(JsRedirection::from)
+                .collect(),
+        })
+    }
+
+    /// What this cleaner did since it was constructed or [`reset_stats`][Self::reset_stats]
+    /// was called.
+    pub fn stats(&self) -> JsStats {
+        JsStats(self.stats.get())
+    }
+
+    /// Set the counts of [`stats`][Self::stats] to zero.
+    #[wasm_bindgen(js_name = resetStats)]
+    pub fn reset_stats(&self) {
+        self.stats.set(Stats::default());
+    }
+}
+
+impl JsUrlCleaner {
+    fn clean_and_record<'a>(&self, url: &'a str) -> Result<Report<'a>, crate::Error> {
+        let report = self.cleaner.clear_url_with_report(url)?;
+        let mut stats = self.stats.get();
+        stats.record(&report);
+        self.stats.set(stats);
+        Ok(report)
+    }
+}
+
+impl From<UrlCleaner> for JsUrlCleaner {
+    fn from(cleaner: UrlCleaner) -> Self {
+        Self {
+            cleaner,
+            stats: Cell::default(),
+        }
+    }
+}
+
+/// A [`Report`][crate::Report] for JavaScript, which is called `Report` there.
+#[wasm_bindgen(js_name = Report, getter_with_clone)]
+#[derive(Debug)]
+pub struct JsReport {
+    /// The cleaned URL
+    pub url: String,
+    /// The names of the providers that matched
+    pub providers: Vec<String>,
+    /// The query and fragment parameters that were removed
+    #[wasm_bindgen(js_name = removedParameters)]
+    pub removed_parameters: Vec<String>,
+    /// The redirections that were followed, in their order
+    pub redirections: Vec<JsRedirection>,
+}
+
+/// A [`Redirection`] for JavaScript, which is called `Redirection` there.
+#[wasm_bindgen(js_name = Redirection, getter_with_clone)]
+#[derive(Debug, Clone)]
+pub struct JsRedirection {
+    /// The name of the provider or email security gateway
+    pub provider: String,
+    /// The redirection pattern that matched, if a provider found the redirection
+    pub pattern: Option<String>,
+    /// How often the target was decoded
+    pub decodes: usize,
+    /// The target, before it was cleaned
+    pub target: String,
+}
+
+impl From<Redirection> for JsRedirection {
+    fn from(r: Redirection) -> Self {
+        Self {
+            provider: r.provider,
+            pattern: r.pattern,
+            decodes: r.decodes,
+            target: r.target,
+        }
+    }
+}
+
+/// [`Stats`] for JavaScript, which is called `Stats` there.
+#[wasm_bindgen(js_name = Stats)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsStats(Stats);
+
+#[wasm_bindgen(js_class = Stats)]
+impl JsStats {
+    /// Construct stats with all counts at zero.
+    #[wasm_bindgen(constructor)]
+    #[must_use]
+    pub fn new() -> JsStats {
+        Self::default()
+    }
+
+    /// How many URLs were cleaned
+    #[wasm_bindgen(getter)]
+    #[must_use]
+    pub fn urls(&self) -> usize {
+        self.0.urls
+    }
+
+    /// How many query and fragment parameters were removed
+    #[wasm_bindgen(getter, js_name = removedParameters)]
+    #[must_use]
+    pub fn removed_parameters(&self) -> usize {
+        self.0.removed_parameters
+    }
+
+    /// How many redirections were followed
+    #[wasm_bindgen(getter)]
+    #[must_use]
+    pub fn redirections(&self) -> usize {
+        self.0.redirections
+    }
+
+    /// The removed parameters and followed redirections together, see [`Stats::total`].
+    #[wasm_bindgen(getter)]
+    #[must_use]
+    pub fn total(&self) -> usize {
+        self.0.total()
+    }
+
+    /// Add what cleaning a URL did to the counts.
+    pub fn record(&mut self, report: &JsReport) {
+        self.0.urls += 1;
+        self.0.removed_parameters += report.removed_parameters.len();
+        self.0.redirections += report.redirections.len();
+    }
+
+    /// Set all counts to zero.
+    pub fn reset(&mut self) {
+        self.0.reset();
+    }
+}
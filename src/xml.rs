@@ -0,0 +1,140 @@
+//! Rewriting attribute values and text content of XML documents, keeping everything else
+//! byte for byte.
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use quick_xml::escape::{escape, partial_escape, unescape};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+use crate::text::{to_str, Edits};
+use crate::UrlCleaner;
+
+/// Rewrite `xml` with callbacks that return the new, unescaped values.
+///
+/// `attribute` is called with the qualified names of each element and attribute, and the
+/// attribute's value. `content` is called with the qualified name of each element without
+/// child elements, and its text, which may be in a single CDATA section.
+pub(crate) fn rewrite(
+    xml: &str,
+    mut attribute: impl FnMut(&str, &str, &str) -> Option<String>,
+    mut content: impl FnMut(&str, &str) -> Option<String>,
+) -> Result<Cow<'_, str>, quick_xml::Error> {
+    let mut reader = Reader::from_str(xml);
+    let mut edits = Edits::new(xml.as_bytes());
+    // the names of the open elements, where their content starts, and whether it has elements
+    let mut elements: Vec<(&str, usize, bool)> = Vec::new();
+    loop {
+        let start = position(&reader);
+        let event = reader.read_event()?;
+        let end = position(&reader);
+        let empty = matches!(event, Event::Empty(_));
+        match event {
+            Event::Start(tag) | Event::Empty(tag) => {
+                if let Some((.., children)) = elements.last_mut() {
+                    *children = true;
+                }
+                let name = name(xml, &tag);
+                for (range, result) in rewrite_attributes(xml, &tag, name, &mut attribute)? {
+                    edits.replace(range, result.as_bytes());
+                }
+                if !empty {
+                    elements.push((name, end, false));
+                }
+            }
+            Event::End(_) => {
+                if let Some((name, content_start, false)) = elements.pop() {
+                    let raw = &xml[content_start..start];
+                    if let Some(result) = rewrite_content(name, raw, &mut content) {
+                        edits.replace(content_start..start, result.as_bytes());
+                    }
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(to_str(xml, edits.finish()))
+}
+
+fn position(reader: &Reader<&[u8]>) -> usize {
+    usize::try_from(reader.buffer_position()).unwrap_or(usize::MAX)
+}
+
+/// The qualified name of `tag`, borrowed from `xml`.
+fn name<'a>(xml: &'a str, tag: &BytesStart<'_>) -> &'a str {
+    let name = tag.name();
+    let start = offset(xml, name.as_ref());
+    &xml[start..start + name.as_ref().len()]
+}
+
+/// Where `slice`, which is part of `xml`, starts in it.
+fn offset(xml: &str, slice: &str) -> usize {
+    slice.as_ptr() as usize - xml.as_ptr() as usize
+}
+
+fn rewrite_attributes(
+    xml: &str,
+    tag: &BytesStart<'_>,
+    name: &str,
+    attribute: &mut impl FnMut(&str, &str, &str) -> Option<String>,
+) -> Result<Vec<(Range<usize>, String)>, quick_xml::Error> {
+    let mut edits = Vec::new();
+    for attr in tag.attributes() {
+        let attr = attr?;
+        let Cow::Borrowed(raw) = attr.value else {
+            continue;
+        };
+        let start = offset(xml, raw);
+        let key = attr.key.as_ref();
+        let value = unescape(raw)?;
+        if let Some(result) = attribute(name, key, &value) {
+            edits.push((start..start + raw.len(), escape(result).into_owned()));
+        }
+    }
+    Ok(edits)
+}
+
+fn rewrite_content(
+    name: &str,
+    raw: &str,
+    content: &mut impl FnMut(&str, &str) -> Option<String>,
+) -> Option<String> {
+    let trimmed = raw.trim();
+    if let Some(cdata) = trimmed
+        .strip_prefix("<![CDATA[")
+        .and_then(|cdata| cdata.strip_suffix("]]>"))
+        .filter(|cdata| !cdata.contains("]]>"))
+    {
+        let result = content(name, cdata)?;
+        let start = raw.len() - raw.trim_start().len();
+        let end = start + trimmed.len();
+        let result = result.replace("]]>", "]]]]><![CDATA[>");
+        return Some([&raw[..start], "<![CDATA[", &result, "]]>", &raw[end..]].concat());
+    }
+    if raw.contains('<') {
+        return None;
+    }
+    let text = unescape(raw).ok()?;
+    let result = content(name, &text)?;
+    Some(partial_escape(result).into_owned())
+}
+
+/// The name without its namespace prefix, like `encoded` of `content:encoded`.
+pub(crate) fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+/// The cleaned URL, with the whitespace around it, if it changed.
+pub(crate) fn clean_url(cleaner: &UrlCleaner, text: &str) -> Option<String> {
+    let url = text.trim();
+    let result = cleaner.clear_url(url).ok()?;
+    if result == url {
+        return None;
+    }
+    let start = text.len() - text.trim_start().len();
+    Some([&text[..start], &result, &text[start + url.len()..]].concat())
+}